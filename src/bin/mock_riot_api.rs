@@ -0,0 +1,61 @@
+// extern crate definitions
+#[macro_use]
+extern crate error_chain;
+extern crate tokio;
+
+use lol_match_crawler::mock_api;
+use std::env;
+use std::path::PathBuf;
+
+fn usage(){
+    println!("Usage: mock_riot_api [listen address (default 127.0.0.1:8080)] [fixtures directory (default ./fixtures)]")
+}
+
+error_chain!{
+    links {
+        MockApi(mock_api::Error, mock_api::ErrorKind);
+    }
+
+    foreign_links {
+        AddrParse(::std::net::AddrParseError);
+    }
+}
+
+async fn do_main() -> Result<()> {
+
+    // ensure proper number of args
+    let args : Vec<String> = env::args().collect();
+    if args.len() > 3 {
+        usage();
+        return Err(Error::from(format!("Invalid number of command line arguments. Expected at most 2, got {}", args.len() - 1)));
+    }
+
+    let addr = args.get(1).map(|s| s.as_str()).unwrap_or("127.0.0.1:8080").parse()?;
+    let mut config = mock_api::MockApiConfig::default();
+    if let Some(dir) = args.get(2) {
+        config.fixtures_dir = PathBuf::from(dir);
+    }
+
+    let local_addr = mock_api::spawn(addr, config)?;
+    println!("mock riot api listening on http://{}", local_addr);
+
+    // serve until killed
+    tokio::signal::ctrl_c().await.chain_err(|| "Unable to listen for ctrl-c")?;
+    Ok(())
+}
+
+/// Workaround to integrate error-chain with async main function
+/// in tokio. Pretty much just an expansion of the `quick_main!`
+/// macro provided by error-chain
+#[tokio::main]
+async fn main() {
+    if let Err(ref e) = do_main().await {
+        use error_chain::ChainedError;
+        use std::io::Write; // trait which holds `display_chain`
+        let stderr = &mut ::std::io::stderr();
+        let errmsg = "Error writing to stderr";
+
+        writeln!(stderr, "{}", e.display_chain()).expect(errmsg);
+        ::std::process::exit(1);
+    }
+}
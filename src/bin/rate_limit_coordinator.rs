@@ -0,0 +1,53 @@
+// extern crate definitions
+#[macro_use]
+extern crate error_chain;
+extern crate tokio;
+
+use lol_match_crawler::lol_api;
+use std::env;
+use std::path::Path;
+
+fn usage(){
+    println!("Usage: rate_limit_coordinator [socket path (default {})]", lol_api::coordinator::DEFAULT_SOCKET_PATH)
+}
+
+error_chain!{
+    links {
+        LolApi(lol_api::Error, lol_api::ErrorKind);
+    }
+}
+
+async fn do_main() -> Result<()> {
+
+    // ensure proper number of args
+    let args : Vec<String> = env::args().collect();
+    if args.len() > 2 {
+        usage();
+        return Err(Error::from(format!("Invalid number of command line arguments. Expected at most 1, got {}", args.len() - 1)));
+    }
+
+    let path = Path::new(args.get(1).map(|s| s.as_str()).unwrap_or(lol_api::coordinator::DEFAULT_SOCKET_PATH));
+    lol_api::coordinator::spawn(path).await?;
+    println!("rate-limit coordinator listening on {}", path.display());
+
+    // serve until killed, then clean up the socket
+    tokio::signal::ctrl_c().await.chain_err(|| "Unable to listen for ctrl-c")?;
+    std::fs::remove_file(path).chain_err(|| format!("Unable to remove {}", path.display()))?;
+    Ok(())
+}
+
+/// Workaround to integrate error-chain with async main function
+/// in tokio. Pretty much just an expansion of the `quick_main!`
+/// macro provided by error-chain
+#[tokio::main]
+async fn main() {
+    if let Err(ref e) = do_main().await {
+        use error_chain::ChainedError;
+        use std::io::Write; // trait which holds `display_chain`
+        let stderr = &mut ::std::io::stderr();
+        let errmsg = "Error writing to stderr";
+
+        writeln!(stderr, "{}", e.display_chain()).expect(errmsg);
+        ::std::process::exit(1);
+    }
+}
@@ -0,0 +1,67 @@
+// extern crate definitions
+#[macro_use]
+extern crate error_chain;
+extern crate tokio;
+
+use lol_match_crawler::{lol_api, proxy, util};
+use std::env;
+
+fn usage(){
+    println!("Usage: riot_api_proxy [listen address (default 127.0.0.1:8081)] [upstream base uri (default {})]", lol_api::RIOT_BASE_URI)
+}
+
+error_chain!{
+    links {
+        Proxy(proxy::Error, proxy::ErrorKind);
+    }
+
+    foreign_links {
+        AddrParse(::std::net::AddrParseError);
+    }
+}
+
+async fn do_main() -> Result<()> {
+
+    // ensure proper number of args
+    let args : Vec<String> = env::args().collect();
+    if args.len() > 3 {
+        usage();
+        return Err(Error::from(format!("Invalid number of command line arguments. Expected at most 2, got {}", args.len() - 1)));
+    }
+
+    let addr = args.get(1).map(|s| s.as_str()).unwrap_or("127.0.0.1:8081").parse()?;
+    let base_uri = args.get(2).map(|s| s.as_str()).unwrap_or(lol_api::RIOT_BASE_URI);
+
+    // get api keys from key.txt, one per line
+    let keys = util::get_keys();
+    if keys.is_empty() {
+        return Err(Error::from("No api key found in key.txt"));
+    }
+    let ctx = keys[1..].iter()
+        .fold(lol_api::Context::builder(&keys[0]), |builder, key| builder.add_api_key(key))
+        .base_uri(base_uri)
+        .build();
+
+    let local_addr = proxy::spawn(addr, ctx)?;
+    println!("riot api proxy listening on http://{}, forwarding to {}", local_addr, base_uri);
+
+    // serve until killed
+    tokio::signal::ctrl_c().await.chain_err(|| "Unable to listen for ctrl-c")?;
+    Ok(())
+}
+
+/// Workaround to integrate error-chain with async main function
+/// in tokio. Pretty much just an expansion of the `quick_main!`
+/// macro provided by error-chain
+#[tokio::main]
+async fn main() {
+    if let Err(ref e) = do_main().await {
+        use error_chain::ChainedError;
+        use std::io::Write; // trait which holds `display_chain`
+        let stderr = &mut ::std::io::stderr();
+        let errmsg = "Error writing to stderr";
+
+        writeln!(stderr, "{}", e.display_chain()).expect(errmsg);
+        ::std::process::exit(1);
+    }
+}
@@ -10,4 +10,11 @@ error_chain!{
         JoinError(::tokio::task::JoinError);
     }
 
+    errors {
+        CrawlStopped {
+            description("Crawl was stopped.")
+            display("Crawl was stopped because the api key was rejected.")
+        }
+    }
+
 }
\ No newline at end of file
@@ -0,0 +1,204 @@
+//! The starting conditions the crawler records for each
+//! participant of a game. The extraction is kept apart from the
+//! crawl so a live game (from `spectator_v4`) can be turned into
+//! the exact same csv columns as a finished match, e.g. to score
+//! it with a model trained on the crawler's output before the game ends.
+//!
+//! A live game doesn't know some of the features yet (the lane and role
+//! are only assigned once the game is over) or doesn't have them at all
+//! (the legacy masteries and runes, and the season tier), so those
+//! are left empty, which is what the api gives back for them in
+//! recent matches anyway. Likewise a match-v5 match has no legacy
+//! masteries, runes or season tier, but gives the runes as perks.
+
+use crate::lol_api::{match_v4, match_v5, spectator_v4};
+
+/// The features of a single participant, in the order
+/// they're written to the output
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ParticipantFeatures {
+    pub champion_id : i64,
+    pub spell1_id : i64,
+    pub spell2_id : i64,
+    pub masteries : Vec<(i32, i32)>,            // (mastery id, rank)
+    pub runes : Vec<(i32, i32)>,                // (rune id, rank)
+    pub perks : Vec<i32>,                       // match-v5 rune styles and selections, then the stat perks
+    pub highest_achieved_season_tier : String,
+    pub lane : String,
+    pub role : String,
+    pub mastery_points : Option<i64>,           // only filled in by the crawler's optional enrichment
+}
+
+impl ParticipantFeatures {
+
+    /// Picks the features out of a participant of a finished match
+    pub fn from_match_participant(participant : &match_v4::ParticipantDto) -> ParticipantFeatures {
+        ParticipantFeatures {
+            champion_id : participant.champion_id.into(),
+            spell1_id : participant.spell1_id.into(),
+            spell2_id : participant.spell2_id.into(),
+            masteries : participant.masteries.iter().map(|m| (m.mastery_id, m.rank)).collect(),
+            runes : participant.runes.iter().map(|r| (r.rune_id, r.rank)).collect(),
+            perks : Vec::new(),
+            highest_achieved_season_tier : participant.highest_achieved_season_tier.clone(),
+            lane : participant.timeline.lane.clone(),
+            role : participant.timeline.role.clone(),
+            mastery_points : None,
+        }
+    }
+
+    /// Picks the features out of a participant of a finished match-v5 match
+    pub fn from_match_v5_participant(participant : &match_v5::ParticipantDto) -> ParticipantFeatures {
+        let mut perks = Vec::new();
+        for style in &participant.perks.styles {
+            perks.push(style.style);
+            perks.extend(style.selections.iter().map(|selection| selection.perk));
+        }
+        let stat_perks = &participant.perks.stat_perks;
+        perks.extend(&[stat_perks.offense, stat_perks.flex, stat_perks.defense]);
+
+        ParticipantFeatures {
+            champion_id : participant.champion_id.into(),
+            spell1_id : participant.summoner1_id.into(),
+            spell2_id : participant.summoner2_id.into(),
+            perks,
+            lane : participant.lane.clone(),
+            role : participant.role.clone(),
+            ..ParticipantFeatures::default()
+        }
+    }
+
+    /// Picks the features out of a participant of a live game
+    pub fn from_current_game_participant(participant : &spectator_v4::CurrentGameParticipantDto) -> ParticipantFeatures {
+        ParticipantFeatures {
+            champion_id : participant.champion_id,
+            spell1_id : participant.spell1_id,
+            spell2_id : participant.spell2_id,
+            ..ParticipantFeatures::default()
+        }
+    }
+
+    /// Appends the features to a csv line's items
+    ///
+    /// # Arguments
+    ///
+    /// `line_items` - the items of the line being built
+    pub fn push_line_items(&self, line_items : &mut Vec<String>) {
+
+        // champ
+        line_items.push(self.champion_id.to_string());
+
+        //spells
+        line_items.push(self.spell1_id.to_string());
+        line_items.push(self.spell2_id.to_string());
+
+        //masteries
+        for (mastery_id, rank) in &self.masteries {
+            line_items.push(mastery_id.to_string());
+            line_items.push(rank.to_string());
+        }
+
+        //runes
+        for (rune_id, rank) in &self.runes {
+            line_items.push(rune_id.to_string());
+            line_items.push(rank.to_string());
+        }
+        for perk in &self.perks {
+            line_items.push(perk.to_string());
+        }
+
+        // highest achieved season tier
+        line_items.push(self.highest_achieved_season_tier.clone());
+
+        //role and lane
+        line_items.push(self.lane.clone());
+        line_items.push(self.role.clone());
+
+        // mastery points on the champion, if enriched
+        if let Some(mastery_points) = self.mastery_points {
+            line_items.push(mastery_points.to_string());
+        }
+    }
+}
+
+/// Gets the features of every participant of a finished match,
+/// in participant order
+pub fn match_features(match_dto : &match_v4::MatchDto) -> Vec<ParticipantFeatures> {
+    match_dto.participants.iter().map(ParticipantFeatures::from_match_participant).collect()
+}
+
+/// Gets the features of every participant of a finished
+/// match-v5 match, in participant order
+pub fn match_v5_features(match_dto : &match_v5::MatchDto) -> Vec<ParticipantFeatures> {
+    match_dto.info.participants.iter().map(ParticipantFeatures::from_match_v5_participant).collect()
+}
+
+/// Gets the features of every participant of a live game,
+/// in participant order
+pub fn current_game_features(game : &spectator_v4::CurrentGameInfoDto) -> Vec<ParticipantFeatures> {
+    game.participants.iter().map(ParticipantFeatures::from_current_game_participant).collect()
+}
+
+/// Gets the csv line items for a whole game
+///
+/// # Arguments
+///
+/// `participants` - the features of every participant of the game, in order
+pub fn line_items(participants : &[ParticipantFeatures]) -> Vec<String> {
+    let mut line_items = Vec::new();
+    for participant in participants {
+        participant.push_line_items(&mut line_items);
+    }
+    line_items
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{match_features, match_v5_features, current_game_features, line_items};
+    use crate::lol_api::{match_v4, match_v5, spectator_v4};
+
+    /// Checks a live game gives the same columns as the finished
+    /// match it turns into, minus the post-game ones
+    #[test]
+    fn test_live_game_matches_match_columns() {
+
+        let match_dto : match_v4::MatchDto = serde_json::from_str(r#"{"participants":[
+            {"championId":1,"spell1Id":4,"spell2Id":12,"highestAchievedSeasonTier":"GOLD","timeline":{"lane":"TOP","role":"SOLO"}},
+            {"championId":103,"spell1Id":4,"spell2Id":14,"highestAchievedSeasonTier":"SILVER","timeline":{"lane":"MIDDLE","role":"SOLO"}}]}"#).unwrap();
+        let game : spectator_v4::CurrentGameInfoDto = serde_json::from_str(r#"{"participants":[
+            {"championId":1,"spell1Id":4,"spell2Id":12},
+            {"championId":103,"spell1Id":4,"spell2Id":14}]}"#).unwrap();
+
+        assert_eq!(line_items(&match_features(&match_dto)), vec!["1", "4", "12", "GOLD", "TOP", "SOLO", "103", "4", "14", "SILVER", "MIDDLE", "SOLO"]);
+        assert_eq!(line_items(&current_game_features(&game)), vec!["1", "4", "12", "", "", "", "103", "4", "14", "", "", ""]);
+    }
+
+    /// Checks a match-v5 match gives the same columns as a match-v4
+    /// one, with the perks in place of the legacy masteries and runes
+    #[test]
+    fn test_match_v5_columns() {
+
+        let match_dto : match_v5::MatchDto = serde_json::from_str(r#"{"info":{"participants":[
+            {"championId":1,"summoner1Id":4,"summoner2Id":12,"lane":"TOP","role":"SOLO","perks":{
+                "statPerks":{"offense":5008,"flex":5008,"defense":5002},
+                "styles":[{"style":8000,"selections":[{"perk":8010},{"perk":9111}]},{"style":8400,"selections":[{"perk":8473}]}]}}]}}"#).unwrap();
+
+        assert_eq!(line_items(&match_v5_features(&match_dto)),
+                   vec!["1", "4", "12", "8000", "8010", "9111", "8400", "8473", "5008", "5008", "5002", "", "TOP", "SOLO"]);
+    }
+
+    /// Checks enriched mastery points are appended to each participant
+    #[test]
+    fn test_mastery_points_column() {
+
+        let game : spectator_v4::CurrentGameInfoDto = serde_json::from_str(r#"{"participants":[
+            {"championId":1,"spell1Id":4,"spell2Id":12},
+            {"championId":103,"spell1Id":4,"spell2Id":14}]}"#).unwrap();
+
+        let mut participants = current_game_features(&game);
+        participants[0].mastery_points = Some(1200);
+        participants[1].mastery_points = Some(0);
+        assert_eq!(line_items(&participants), vec!["1", "4", "12", "", "", "", "1200", "103", "4", "14", "", "", "", "0"]);
+    }
+}
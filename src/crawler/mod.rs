@@ -9,23 +9,38 @@
 //! not storing data redundantly.
 
 mod errors;
+pub mod features;
 pub use errors::*;
 
 use crate::lol_api;
 use std::collections::HashSet;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use tokio::sync::Mutex;
 
+/// Optional steps and settings of the crawl. They're all off by default
+/// since most of them cost extra queries for every match.
+#[derive(Debug, Clone, Default)]
+pub struct CrawlerOptions {
+    pub mastery_points : bool,                          // append each participant's mastery points on their champion (one query per participant)
+    pub matchlist_filter : lol_api::MatchlistFilter,    // only crawl the matches the filter keeps (e.g. ranked solo in a patch window)
+    pub output_dir : Option<PathBuf>,                   // the directory to write the output file to, the current directory if not set
+}
+
 /// The inner data of a single crawler which lives across
 /// threads. Creating a new crawler instantiates
 /// one inner data, and cloning the crawler reuses
 /// the inner data for running multiple crawlers at once.
 struct CrawlerInner {
     context : lol_api::Context,
+    options : CrawlerOptions,
     file_out : Mutex<File>,
     found_match_ids : Mutex<HashSet<i64>>,
+    found_match_v5_ids : Mutex<HashSet<String>>,
+    stopped : AtomicBool,                       // set once the api key is rejected, which stops every crawl sharing this data
 }
 
 /// A thin Arc wrapper which holds an Arc to the inner
@@ -57,13 +72,30 @@ impl Crawler {
     /// `Err(errors::Error)` if the construction failed (likely
     /// because the os couldn't open the output file for writing)
     pub async fn new(context : lol_api::Context) -> Result<Crawler> {
-        let f_name = format!("./lol_data-{}", chrono::Utc::now().format("%F-%H-%M-%S"));
+        Self::with_options(context, CrawlerOptions::default()).await
+    }
+
+    /// ctor which also turns on the optional steps of the
+    /// crawl. Otherwise the same as `new`.
+    /// 
+    /// # Arguments
+    /// 
+    /// `context` - the lol api context to use for the crawler
+    ///             The context is moved in
+    /// `options` - the optional steps to run for each match
+    pub async fn with_options(context : lol_api::Context, options : CrawlerOptions) -> Result<Crawler> {
+        let output_dir = options.output_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+        tokio::fs::create_dir_all(&output_dir).await?;
+        let f_name = output_dir.join(format!("lol_data-{}", chrono::Utc::now().format("%F-%H-%M-%S")));
         let file_out = File::create(f_name).await?;
         Ok(Crawler {
             inner : Arc::new(CrawlerInner {
                 context : context,
+                options,
                 file_out : Mutex::new(file_out),
                 found_match_ids : Mutex::new(HashSet::new()),
+                found_match_v5_ids : Mutex::new(HashSet::new()),
+                stopped : AtomicBool::new(false),
             })
         })
     }
@@ -77,8 +109,9 @@ impl Crawler {
     /// 
     /// # Arguments
     /// 
-    /// * `seed_summoner_name` - the summoner name to use for getting the first
-    ///     match history to crawl.
+    /// * `region` - the region to crawl. The crawl never leaves this region.
+    /// * `seed` - the summoner name or riot id (`name#tag`) of the player whose
+    ///     match history is crawled first.
     /// * `num_steps` - The number of matches to fetch in total. If the result is
     ///     an error, then up to this many matches may still have been recorded in the
     ///     output file.
@@ -88,18 +121,106 @@ impl Crawler {
     /// `Ok(())` if `num_steps` number of matches were succesfully recorded to the file
     /// `Err(errors::Error)` if less than the `num_steps` number of matches output
     /// 
-    pub async fn start_crawl(&self, seed_summoner_name : &str, num_steps : usize) -> Result<()> {
+    pub async fn start_crawl(&self, region : lol_api::Region, seed : &str, num_steps : usize) -> Result<()> {
+
+        let result = Self::crawl_from_seed(self.inner.clone(), region, seed, num_steps).await;
+        Self::stop_if_rejected(&self.inner, result)
+    }
+
+    /// The body of `start_crawl`, split out so any error it
+    /// ends with can be checked for a rejected api key
+    async fn crawl_from_seed(inner : Arc<CrawlerInner>, region : lol_api::Region, seed : &str, num_steps : usize) -> Result<()> {
 
-        let seed_account_id = self.inner.context
-                                .query_summoner_v4_by_summoner_name(lol_api::Region::Na1, seed_summoner_name, 3).await
+        Self::check_stopped(&inner)?;
+        let seed_account_id = Self::query_seed_summoner(inner.clone(), region, seed).await
                                 .chain_err(|| "Unable to get seed summoner id.")?
                                 .account_id;
 
-        // first get an unkown seed match id
-        let matchlist_dto = self.inner.context.query_match_v4_matchlist_by_account(lol_api::Region::Na1, &seed_account_id, 3).await?;
-        let seed_match_id = Self::reserve_new_match_id(self.inner.clone(), &matchlist_dto).await.unwrap();
+        // the first match is an unkown one from the seed's history
+        let matchlist_dto = inner.context.query_match_v4_matchlist_by_account_with_filter(region, &seed_account_id, &inner.options.matchlist_filter).await?;
 
-        Self::do_crawl_work(self.inner.clone(), num_steps, seed_match_id).await
+        Self::do_crawl_work(inner, region, num_steps, matchlist_dto).await
+    }
+
+    /// Stops every crawl sharing the inner data if the api key
+    /// was rejected (a 401 or 403), since every query after that
+    /// would be rejected too.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `inner` - the crawler's inner data
+    /// * `result` - the result a crawl ended with
+    /// 
+    /// # Return
+    /// 
+    /// The result, unchanged
+    fn stop_if_rejected(inner : &CrawlerInner, result : Result<()>) -> Result<()> {
+
+        if let Err(e) = &result {
+            let mut cause : Option<&(dyn std::error::Error + 'static)> = Some(e);
+            while let Some(err) = cause {
+                let kind = match err.downcast_ref::<Error>().map(|e| e.kind()) {
+                    Some(ErrorKind::LolApi(kind)) => Some(kind),
+                    _ => err.downcast_ref::<lol_api::Error>().map(|e| e.kind()),
+                };
+                if let Some(lol_api::ErrorKind::Unauthorized(..)) = kind {
+                    inner.stopped.store(true, Ordering::SeqCst);
+                    break;
+                }
+                cause = err.source();
+            }
+        }
+        result
+    }
+
+    /// Checks whether the crawls sharing the inner data were stopped
+    /// 
+    /// # Return
+    /// 
+    /// `Err(ErrorKind::CrawlStopped)` if they were, `Ok(())` otherwise
+    fn check_stopped(inner : &CrawlerInner) -> Result<()> {
+        if inner.stopped.load(Ordering::SeqCst) {
+            return Err(ErrorKind::CrawlStopped.into());
+        }
+        Ok(())
+    }
+
+    /// Resolves a crawl seed to the seed player's summoner. Seeds
+    /// containing a `#` are riot ids, which we resolve through
+    /// account-v1 since summoner names no longer uniquely
+    /// identify players.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `inner` - the crawler's inner data
+    /// * `region` - the region the seed player plays in
+    /// * `seed` - the summoner name or riot id (`name#tag`) of the seed player
+    async fn query_seed_summoner(inner : Arc<CrawlerInner>, region : lol_api::Region, seed : &str) -> Result<lol_api::SummonerDto> {
+
+        match lol_api::account_v1::split_riot_id(seed) {
+            Some(_) => {
+                let puuid = Self::query_seed_puuid(inner.clone(), region, seed).await?;
+                Ok(inner.context.query_summoner_v4_by_puuid(region, &puuid).await?)
+            },
+            None => Ok(inner.context.query_summoner_v4_by_summoner_name(region, seed).await?),
+        }
+    }
+
+    /// Resolves a crawl seed to the seed player's puuid. Like
+    /// `query_seed_summoner`, but riot ids only cost the
+    /// account-v1 query.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `inner` - the crawler's inner data
+    /// * `region` - the region the seed player plays in
+    /// * `seed` - the summoner name or riot id (`name#tag`) of the seed player
+    async fn query_seed_puuid(inner : Arc<CrawlerInner>, region : lol_api::Region, seed : &str) -> Result<String> {
+
+        match lol_api::account_v1::split_riot_id(seed) {
+            Some((game_name, tag_line)) => Ok(inner.context.query_account_v1_by_riot_id(region, game_name, tag_line).await?.puuid),
+            None => Ok(inner.context.query_summoner_v4_by_summoner_name(region, seed).await?.puuid),
+        }
     }
 
     /// Consolidates the steps of both crawling a match history
@@ -146,13 +267,16 @@ impl Crawler {
     /// # Return
     /// 
     /// A string slice referring to the encrypted account id of the random
-    /// participant inside the provided `match_dto`
-    fn random_account_id<'a>(match_dto : &'a lol_api::MatchDto) -> &'a str {
+    /// participant inside the provided `match_dto`, or an error
+    /// if the match lists no participants
+    fn random_account_id<'a>(match_dto : &'a lol_api::MatchDto) -> Result<&'a str> {
 
-        let participant_idx = rand::random::<usize>() % match_dto.participant_identities.len();
-        &match_dto.participant_identities
-                 .get(participant_idx).unwrap()
-                 .player.account_id
+        let identities = &match_dto.participant_identities;
+        if identities.is_empty() {
+            return Err(Error::from("Crawl reached a match with no participants."));
+        }
+        let participant_idx = rand::random::<usize>() % identities.len();
+        Ok(&identities[participant_idx].player.account_id)
     }
 
     /// Runs the algorithm to crawl and do the heavy lifting.
@@ -170,8 +294,14 @@ impl Crawler {
     /// * `inner` - the crawler's inner data to avoid tying
     ///     this to an instance of the crawler so it can run
     ///     on another thread
+    /// * `region` - the region the matches are in
     /// * `match_count` - how many matches should be fetched
-    /// * `seed_match_id` - the first match to record
+    /// * `seed_matchlist_dto` - the match history to take the first match from
+    /// 
+    /// # Remarks
+    /// 
+    /// Matches which 404 (e.g. they were removed) are skipped in favour
+    /// of the next unseen match of the same match history.
     /// 
     /// # Return
     /// 
@@ -181,21 +311,29 @@ impl Crawler {
     ///   reached a "dead end" in the course of the crawl (e.g. edge case 
     ///   where summoner only has one match in their match history).
     async fn do_crawl_work(
-        inner : Arc<CrawlerInner>,
-        match_count : usize, seed_match_id : i64) -> Result<()>{
+        inner : Arc<CrawlerInner>, region : lol_api::Region,
+        match_count : usize, seed_matchlist_dto : lol_api::MatchlistDto) -> Result<()>{
+
+        let mut matchlist_dto = seed_matchlist_dto;
+        let mut recorded = 0;
+        while recorded < match_count {
 
-        let mut match_id = seed_match_id;
-        for i in 0..match_count {
+            Self::check_stopped(&inner)?;
+            let match_id = Self::reserve_new_match_id(inner.clone(), &matchlist_dto).await
+                               .chain_err(|| "Crawl reached a player with no unseen matches.")?;
 
             // get match, record data, and add to 'seen' set
-            let match_dto = inner.context.query_match_v4_match_by_id(lol_api::Region::Na1, match_id, 3).await?;
-            Self::write_match_to_file(inner.clone(), &match_dto).await?;
+            let match_dto = match inner.context.query_match_v4_match_by_id(region, match_id).await {
+                Err(e) if matches!(e.kind(), lol_api::ErrorKind::NotFound(..)) => continue,
+                match_dto => match_dto?,
+            };
+            Self::write_match_to_file(inner.clone(), region, &match_dto).await?;
+            recorded += 1;
 
             // get next match from that participants match history
-            if i != (match_count - 1) {
-                let account_id = Self::random_account_id(&match_dto);
-                let matchlist_dto = inner.context.query_match_v4_matchlist_by_account(lol_api::Region::Na1, account_id, 3).await?;
-                match_id = Self::reserve_new_match_id(inner.clone(), &matchlist_dto).await.unwrap();
+            if recorded != match_count {
+                let account_id = Self::random_account_id(&match_dto)?;
+                matchlist_dto = inner.context.query_match_v4_matchlist_by_account_with_filter(region, account_id, &inner.options.matchlist_filter).await?;
             }
         }
 
@@ -203,61 +341,242 @@ impl Crawler {
     }
 
     /// Selects important data from a match data object
-    /// and writes it asynchrnously to the output file.
+    /// (see `features`) and writes it asynchrnously to the output file.
     /// 
     /// # Arguments
     /// 
     /// * `inner` - the crawler's inner data to avoid tying
     ///     this to an instance of the crawler so it can run
     ///     on another thread
+    /// * `region` - the region the match was played in
     /// * `match_dto` - the match to cherry-pick the data from
     /// 
     /// # Return
     /// 
     /// `Ok(())` if the file was written to sucesfully
     /// `Err(lol_api::Error)` if the file could not be written to
-    ///     (in which case the error wraps an io::Error)
+    ///     (in which case the error wraps an io::Error), or
+    ///     the enrichment queries failed
+    /// 
+    async fn write_match_to_file(inner : Arc<CrawlerInner>, region : lol_api::Region, match_dto : &lol_api::MatchDto) -> Result<()> {
+
+        let mut participants = features::match_features(match_dto);
+
+        if inner.options.mastery_points {
+            for (participant, participant_features) in match_dto.participants.iter().zip(participants.iter_mut()) {
+                let summoner_id = &match_dto.participant_identities.iter()
+                                    .find(|identity| identity.participant_id == participant.participant_id as i64)
+                                    .chain_err(|| "Match is missing a participant identity.")?
+                                    .player.summoner_id;
+                let mastery_points = Self::query_mastery_points(inner.clone(), region, summoner_id, participant.champion_id.into()).await?;
+                participant_features.mastery_points = Some(mastery_points);
+            }
+        }
+
+        Self::write_line_to_file(inner, features::line_items(&participants)).await
+    }
+
+    /// Gets how many mastery points a summoner has on a champion.
+    /// We look the champion up in the summoner's full mastery list
+    /// rather than querying it directly, since the direct query
+    /// responds 404 for champions the summoner has never played.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `inner` - the crawler's inner data
+    /// * `region` - the region the summoner plays in
+    /// * `summoner_id` - the summoner's encrypted summoner id
+    /// * `champion_id` - the champion to get the points of
+    /// 
+    /// # Return
     /// 
-    async fn write_match_to_file(inner : Arc<CrawlerInner>, match_dto : &lol_api::MatchDto) -> Result<()> {
+    /// The mastery points, which are 0 if the summoner never played the champion
+    async fn query_mastery_points(
+        inner : Arc<CrawlerInner>, region : lol_api::Region,
+        summoner_id : &str, champion_id : i64) -> Result<i64> {
+
+        let masteries = inner.context.query_champion_mastery_v4_by_summoner(region, summoner_id).await?;
+        Ok(masteries.iter()
+            .find(|mastery| mastery.champion_id == champion_id)
+            .map(|mastery| mastery.champion_points)
+            .unwrap_or(0))
+    }
 
-        let mut line_items : Vec<String> = Vec::new();
+    /// Begins the crawl for match data using the match-v5 api,
+    /// which identifies players by their puuid. Otherwise the
+    /// crawl is the same as `start_crawl`.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `region` - the region to crawl. The crawl never leaves this region.
+    /// * `seed` - the summoner name or riot id (`name#tag`) of the player whose
+    ///   match history is crawled first.
+    /// * `num_steps` - The number of matches to fetch in total. If the result is
+    ///   an error, then up to this many matches may still have been recorded in the
+    ///   output file.
+    /// 
+    /// # Return
+    /// 
+    /// `Ok(())` if `num_steps` number of matches were succesfully recorded to the file
+    /// `Err(errors::Error)` if less than the `num_steps` number of matches output
+    /// 
+    pub async fn start_crawl_v5(&self, region : lol_api::Region, seed : &str, num_steps : usize) -> Result<()> {
+
+        let result = Self::crawl_from_seed_v5(self.inner.clone(), region, seed, num_steps).await;
+        Self::stop_if_rejected(&self.inner, result)
+    }
 
-        //participant stats
-        for participant in &match_dto.participants {
+    /// The match-v5 version of `crawl_from_seed`
+    async fn crawl_from_seed_v5(inner : Arc<CrawlerInner>, region : lol_api::Region, seed : &str, num_steps : usize) -> Result<()> {
 
-            // champ
-            line_items.push(participant.champion_id.to_string());
+        Self::check_stopped(&inner)?;
+        let seed_puuid = Self::query_seed_puuid(inner.clone(), region, seed).await
+                            .chain_err(|| "Unable to get seed summoner puuid.")?;
 
-            //spells
-            line_items.push(participant.spell1_id.to_string());
-            line_items.push(participant.spell2_id.to_string());
+        // the first match is an unkown one from the seed's history
+        let match_ids = inner.context.query_match_v5_match_ids_by_puuid(region, &seed_puuid).await?;
 
-            //masteries
-            for mastery in &participant.masteries {
-                line_items.push(mastery.mastery_id.to_string());
-                line_items.push(mastery.rank.to_string());
-            }
+        Self::do_crawl_work_v5(inner, region, num_steps, match_ids).await
+    }
+
+    /// The match-v5 version of `reserve_new_match_id`
+    /// 
+    /// # Arguments
+    /// 
+    /// * `inner` - the crawler's inner data to avoid tying
+    ///   this to an instance of the crawler so it can run
+    ///   on another thread
+    /// * `match_ids` - the previously-fetched match ids
+    ///   for a player
+    /// 
+    /// # Return
+    /// 
+    /// Some(String) containing the found match id
+    /// None if the match ids contain no unseen matches
+    /// 
+    async fn reserve_new_match_v5_id(inner : Arc<CrawlerInner>, match_ids : &[String]) -> Option<String> {
+
+        let mut found_match_ids = inner.found_match_v5_ids.lock().await;
+        let first_unkown = match_ids.iter().find(|id| !found_match_ids.contains(*id))?;
+
+        found_match_ids.insert(first_unkown.clone());
+        Some(first_unkown.clone())
+    }
+
+    /// Takes a match and selects one of the match participants at random
+    /// and gives us back their puuid
+    /// 
+    /// # Arguments
+    /// 
+    /// * `match_dto` - a reference to the match dto to select a player from
+    /// 
+    /// # Return
+    /// 
+    /// A string slice referring to the puuid of the random
+    /// participant inside the provided `match_dto`, or an error
+    /// if the match lists no participants
+    fn random_puuid(match_dto : &lol_api::match_v5::MatchDto) -> Result<&str> {
+
+        let participants = &match_dto.metadata.participants;
+        if participants.is_empty() {
+            return Err(Error::from("Crawl reached a match with no participants."));
+        }
+        let participant_idx = rand::random::<usize>() % participants.len();
+        Ok(&participants[participant_idx])
+    }
+
+    /// The match-v5 version of `do_crawl_work`, which walks from
+    /// match to match through the participants' puuids.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `inner` - the crawler's inner data to avoid tying
+    ///   this to an instance of the crawler so it can run
+    ///   on another thread
+    /// * `region` - the region the matches are in
+    /// * `match_count` - how many matches should be fetched
+    /// * `seed_match_ids` - the match ids to take the first match from
+    /// 
+    /// # Return
+    /// 
+    /// * `Ok(())` if `num_matches` was found
+    /// * `Err(lol_api::Error)` if less than num matches were found
+    async fn do_crawl_work_v5(
+        inner : Arc<CrawlerInner>, region : lol_api::Region,
+        match_count : usize, seed_match_ids : Vec<String>) -> Result<()>{
+
+        let mut match_ids = seed_match_ids;
+        let mut recorded = 0;
+        while recorded < match_count {
+
+            Self::check_stopped(&inner)?;
+            let match_id = Self::reserve_new_match_v5_id(inner.clone(), &match_ids).await
+                               .chain_err(|| "Crawl reached a player with no unseen matches.")?;
+
+            // get match, record data, and add to 'seen' set (skipping matches which 404)
+            let match_dto = match inner.context.query_match_v5_match_by_id(region, &match_id).await {
+                Err(e) if matches!(e.kind(), lol_api::ErrorKind::NotFound(..)) => continue,
+                match_dto => match_dto?,
+            };
+            Self::write_match_v5_to_file(inner.clone(), region, &match_dto).await?;
+            recorded += 1;
 
-            //runes
-            for rune in &participant.runes {
-                line_items.push(rune.rune_id.to_string());
-                line_items.push(rune.rank.to_string());
+            // get next match from that participants match history
+            if recorded != match_count {
+                let puuid = Self::random_puuid(&match_dto)?;
+                match_ids = inner.context.query_match_v5_match_ids_by_puuid(region, puuid).await?;
             }
+        }
 
-            // highest achieved season tier
-            line_items.push(participant.highest_achieved_season_tier.clone());
+        Ok(())
+    }
 
-            //role and lane
-            line_items.push(participant.timeline.lane.clone());
-            line_items.push(participant.timeline.role.clone());
+    /// The match-v5 version of `write_match_to_file`. The match-v5 data
+    /// has no masteries or season tier, and gives the runes as perks.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `inner` - the crawler's inner data to avoid tying
+    ///   this to an instance of the crawler so it can run
+    ///   on another thread
+    /// * `region` - the region the match was played in
+    /// * `match_dto` - the match to cherry-pick the data from
+    /// 
+    /// # Return
+    /// 
+    /// `Ok(())` if the file was written to sucesfully
+    /// `Err(lol_api::Error)` if the file could not be written to,
+    ///     or the enrichment queries failed
+    async fn write_match_v5_to_file(inner : Arc<CrawlerInner>, region : lol_api::Region, match_dto : &lol_api::match_v5::MatchDto) -> Result<()> {
+
+        let mut participants = features::match_v5_features(match_dto);
+
+        if inner.options.mastery_points {
+            for (participant, participant_features) in match_dto.info.participants.iter().zip(participants.iter_mut()) {
+                let mastery_points = Self::query_mastery_points(inner.clone(), region, &participant.summoner_id, participant.champion_id.into()).await?;
+                participant_features.mastery_points = Some(mastery_points);
+            }
         }
 
+        Self::write_line_to_file(inner, features::line_items(&participants)).await
+    }
+
+    /// Joins the items into a single csv line and appends it
+    /// to the output file.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `inner` - the crawler's inner data holding the output file
+    /// * `line_items` - the values to write, in order
+    async fn write_line_to_file(inner : Arc<CrawlerInner>, line_items : Vec<String>) -> Result<()> {
+
         // push the line to the output
         let mut line = line_items.join(",");
         line.push('\n');
         
         let mut file_lock = inner.file_out.lock().await;
         file_lock.write_all(&line.into_bytes()).await?;
+        file_lock.flush().await?;
 
         Ok(())
     }
@@ -267,10 +586,33 @@ impl Crawler {
 #[cfg(test)]
 mod tests {
 
-    use super::Crawler;
-    use crate::lol_api::Context;
+    use super::{Crawler, CrawlerOptions, ErrorKind, features};
+    use crate::lol_api::{self, Context, Region};
+    use crate::lol_api::transport::{MockTransport, MockResponse};
+    use crate::mock_api::{self, MockApiConfig};
+    use reqwest::StatusCode;
+    use std::path::PathBuf;
+    use std::sync::Arc;
     use tokio::runtime::Runtime;
 
+    /// Builds a crawler which writes its output to a fresh
+    /// temporary directory rather than the current one
+    async fn temp_crawler(ctx : Context) -> Crawler {
+        temp_crawler_with_options(ctx, CrawlerOptions::default()).await
+    }
+
+    async fn temp_crawler_with_options(ctx : Context, options : CrawlerOptions) -> Crawler {
+        let output_dir = std::env::temp_dir().join(format!("lol-crawler-test-{}", rand::random::<u64>()));
+        Crawler::with_options(ctx, CrawlerOptions { output_dir : Some(output_dir), ..options }).await.unwrap()
+    }
+
+    /// Reads back the lines the crawler wrote to its output file
+    fn output_lines(crawler : &Crawler) -> Vec<String> {
+        let output_dir = crawler.inner.options.output_dir.as_ref().unwrap();
+        let output_file = std::fs::read_dir(output_dir).unwrap().next().unwrap().unwrap().path();
+        std::fs::read_to_string(output_file).unwrap().lines().map(str::to_string).collect()
+    }
+
     /// ctor test for the constructor. 
     /// Makes sure we can do things
     /// like construct the output file 
@@ -278,12 +620,214 @@ mod tests {
     #[test]
     fn test_ctor() {
         let mut rt = Runtime::new().expect("couldn't instantiate tokio runtime!");
-        let key = crate::util::get_key();
-        let ctx = Context::new(&key);
+        let ctx = Context::with_transport("test-key", Arc::new(MockTransport::new()));
 
         rt.block_on(async move {
-            let crawler = Crawler::new(ctx).await;
+            let output_dir = std::env::temp_dir().join(format!("lol-crawler-test-{}", rand::random::<u64>()));
+            let options = CrawlerOptions { output_dir : Some(output_dir), ..CrawlerOptions::default() };
+            let crawler = Crawler::with_options(ctx, options).await;
             assert!(crawler.is_ok());
         });
     }
-}
\ No newline at end of file
+
+    /// Runs a short crawl end to end against the mock riot api server
+    #[test]
+    fn test_crawl_against_mock_server() {
+        let mut rt = Runtime::new().expect("couldn't instantiate tokio runtime!");
+
+        rt.block_on(async move {
+            let config = MockApiConfig {
+                fixtures_dir : PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures")),
+                ..MockApiConfig::default()
+            };
+            let addr = mock_api::spawn("127.0.0.1:0".parse().unwrap(), config).unwrap();
+            let ctx = Context::builder("test-key")
+                .base_uri(&format!("http://{}/{{host}}", addr))
+                .build();
+
+            let crawler = temp_crawler(ctx).await;
+            assert!(crawler.start_crawl(Region::Na1, "hi", 3).await.is_ok());
+        });
+    }
+
+    /// Runs a crawl with the mastery points enrichment against the
+    /// mock riot api server and checks the appended column
+    #[test]
+    fn test_crawl_mastery_points_against_mock_server() {
+        let mut rt = Runtime::new().expect("couldn't instantiate tokio runtime!");
+        let fixtures_dir = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures"));
+
+        let crawler = rt.block_on(async {
+            let config = MockApiConfig {
+                fixtures_dir : fixtures_dir.clone(),
+                ..MockApiConfig::default()
+            };
+            let addr = mock_api::spawn("127.0.0.1:0".parse().unwrap(), config).unwrap();
+            let ctx = Context::builder("test-key")
+                .base_uri(&format!("http://{}/{{host}}", addr))
+                .build();
+
+            let options = CrawlerOptions { mastery_points : true, ..CrawlerOptions::default() };
+            let crawler = temp_crawler_with_options(ctx, options).await;
+            assert!(crawler.start_crawl(Region::Na1, "hi", 1).await.is_ok());
+            crawler
+        });
+
+        // the first unseen match of the seed's history, where the fixture
+        // masteries only have points on the champions of participants 1 and 5
+        let match_json = std::fs::read(fixtures_dir.join("lol/match/v4/matches/3400000003.json")).unwrap();
+        let match_dto : lol_api::MatchDto = serde_json::from_slice(&match_json).unwrap();
+        let mut participants = features::match_features(&match_dto);
+        for (participant, mastery_points) in participants.iter_mut().zip(&[64000, 0, 0, 0, 1200, 0, 0, 0, 0, 0]) {
+            participant.mastery_points = Some(*mastery_points);
+        }
+        assert_eq!(output_lines(&crawler), vec![features::line_items(&participants).join(",")]);
+    }
+
+    /// Runs short crawls seeded by a riot id against the mock riot api server
+    #[test]
+    fn test_crawl_riot_id_seed_against_mock_server() {
+        let mut rt = Runtime::new().expect("couldn't instantiate tokio runtime!");
+
+        rt.block_on(async move {
+            let config = MockApiConfig {
+                fixtures_dir : PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures")),
+                ..MockApiConfig::default()
+            };
+            let addr = mock_api::spawn("127.0.0.1:0".parse().unwrap(), config).unwrap();
+            let ctx = Context::builder("test-key")
+                .base_uri(&format!("http://{}/{{host}}", addr))
+                .build();
+
+            let crawler = temp_crawler(ctx).await;
+            assert!(crawler.start_crawl(Region::Na1, "hi#NA1", 1).await.is_ok());
+            assert!(crawler.start_crawl_v5(Region::Na1, "hi#NA1", 1).await.is_ok());
+        });
+    }
+
+    /// Runs a short match-v5 crawl end to end against the mock riot api server
+    #[test]
+    fn test_crawl_v5_against_mock_server() {
+        let mut rt = Runtime::new().expect("couldn't instantiate tokio runtime!");
+
+        rt.block_on(async move {
+            let config = MockApiConfig {
+                fixtures_dir : PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures")),
+                ..MockApiConfig::default()
+            };
+            let addr = mock_api::spawn("127.0.0.1:0".parse().unwrap(), config).unwrap();
+            let ctx = Context::builder("test-key")
+                .base_uri(&format!("http://{}/{{host}}", addr))
+                .build();
+
+            let crawler = temp_crawler(ctx).await;
+            assert!(crawler.start_crawl_v5(Region::Na1, "hi", 3).await.is_ok());
+        });
+    }
+
+    /// Checks that a match which 404s is skipped for the next
+    /// unseen match of the same history
+    #[test]
+    fn test_crawl_skips_missing_matches() {
+        let mut rt = Runtime::new().expect("couldn't instantiate tokio runtime!");
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/summoner/v4/summoners/by-name/hi",
+            MockResponse::json(r#"{"accountId":"acc-1","profileIconId":1,"revisionDate":0,"name":"hi","id":"sum-1","puuid":"puuid-1","summonerLevel":30}"#));
+        transport.push_response("/lol/match/v4/matchlists/by-account/acc-1",
+            MockResponse::json(r#"{"startIndex":0,"endIndex":2,"totalGames":2,"matches":[
+                {"gameId":1,"role":"SOLO","season":13,"platformId":"NA1","champion":1,"queue":420,"lane":"TOP","timestamp":1},
+                {"gameId":2,"role":"SOLO","season":13,"platformId":"NA1","champion":1,"queue":420,"lane":"TOP","timestamp":0}]}"#));
+        transport.push_response("/lol/match/v4/matches/1", MockResponse::error(StatusCode::NOT_FOUND));
+        transport.push_response("/lol/match/v4/matches/2",
+            MockResponse::json(r#"{"gameId":2,"participantIdentities":[{"participantId":1,"player":{"accountId":"acc-1"}}],"participants":[{"participantId":1,"championId":1,"teamId":100}]}"#));
+        let ctx = Context::with_transport("test-key", transport.clone());
+
+        rt.block_on(async move {
+            let crawler = temp_crawler(ctx).await;
+            assert!(crawler.start_crawl(Region::Na1, "hi", 1).await.is_ok());
+        });
+        assert_eq!(transport.request_count("/lol/match/v4/matches/1"), 1);
+        assert_eq!(transport.request_count("/lol/match/v4/matches/2"), 1);
+    }
+
+    /// Checks that a matchlist filter which leaves no unseen match
+    /// ends the crawl with an error rather than a panic
+    #[test]
+    fn test_crawl_filter_without_unseen_matches() {
+        let mut rt = Runtime::new().expect("couldn't instantiate tokio runtime!");
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/summoner/v4/summoners/by-name/hi",
+            MockResponse::json(r#"{"accountId":"acc-1","profileIconId":1,"revisionDate":0,"name":"hi","id":"sum-1","puuid":"puuid-1","summonerLevel":30}"#));
+        transport.push_response("/lol/match/v4/matchlists/by-account/acc-1?queue=440",
+            MockResponse::json(r#"{"startIndex":0,"endIndex":0,"totalGames":0,"matches":[]}"#));
+        let ctx = Context::with_transport("test-key", transport.clone());
+
+        rt.block_on(async move {
+            let filter = lol_api::MatchlistFilter { queue : vec![440], ..lol_api::MatchlistFilter::default() };
+            let options = CrawlerOptions { matchlist_filter : filter, ..CrawlerOptions::default() };
+            let crawler = temp_crawler_with_options(ctx, options).await;
+            let err = crawler.start_crawl(Region::Na1, "hi", 1).await.unwrap_err();
+            assert!(err.to_string().contains("no unseen matches"));
+        });
+        assert_eq!(transport.request_count("/lol/match/v4/matchlists/by-account/acc-1?queue=440"), 1);
+    }
+
+    /// Checks that a match listing no participants ends the
+    /// crawl with an error rather than a panic
+    #[test]
+    fn test_crawl_match_without_participants() {
+        let mut rt = Runtime::new().expect("couldn't instantiate tokio runtime!");
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/summoner/v4/summoners/by-name/hi",
+            MockResponse::json(r#"{"accountId":"acc-1","profileIconId":1,"revisionDate":0,"name":"hi","id":"sum-1","puuid":"puuid-1","summonerLevel":30}"#));
+        transport.push_response("/lol/match/v4/matchlists/by-account/acc-1",
+            MockResponse::json(r#"{"startIndex":0,"endIndex":1,"totalGames":1,"matches":[
+                {"gameId":1,"role":"SOLO","season":13,"platformId":"NA1","champion":1,"queue":420,"lane":"TOP","timestamp":1}]}"#));
+        transport.push_response("/lol/match/v4/matches/1", MockResponse::json(r#"{"gameId":1,"participantIdentities":[],"participants":[]}"#));
+        let ctx = Context::with_transport("test-key", transport);
+
+        rt.block_on(async move {
+            let crawler = temp_crawler(ctx).await;
+            let err = crawler.start_crawl(Region::Na1, "hi", 2).await.unwrap_err();
+            assert!(err.to_string().contains("no participants"));
+        });
+    }
+
+    /// Checks that a match-v5 match listing no participants ends
+    /// the crawl with an error rather than a panic
+    #[test]
+    fn test_crawl_v5_match_without_participants() {
+        let mut rt = Runtime::new().expect("couldn't instantiate tokio runtime!");
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/summoner/v4/summoners/by-name/hi",
+            MockResponse::json(r#"{"accountId":"acc-1","profileIconId":1,"revisionDate":0,"name":"hi","id":"sum-1","puuid":"puuid-1","summonerLevel":30}"#));
+        transport.push_response("/lol/match/v5/matches/by-puuid/puuid-1/ids", MockResponse::json(r#"["NA1_1"]"#));
+        transport.push_response("/lol/match/v5/matches/NA1_1", MockResponse::json(r#"{"metadata":{"matchId":"NA1_1","participants":[]},"info":{}}"#));
+        let ctx = Context::with_transport("test-key", transport);
+
+        rt.block_on(async move {
+            let crawler = temp_crawler(ctx).await;
+            let err = crawler.start_crawl_v5(Region::Na1, "hi", 2).await.unwrap_err();
+            assert!(err.to_string().contains("no participants"));
+        });
+    }
+
+    /// Checks that a rejected api key stops every crawl sharing
+    /// the crawler's data, without sending any more queries
+    #[test]
+    fn test_crawl_stops_on_rejected_key() {
+        let mut rt = Runtime::new().expect("couldn't instantiate tokio runtime!");
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/summoner/v4/summoners/by-name/hi", MockResponse::error(StatusCode::FORBIDDEN));
+        let ctx = Context::with_transport("test-key", transport.clone());
+
+        rt.block_on(async move {
+            let crawler = temp_crawler(ctx).await;
+            let other = crawler.clone();
+            assert!(crawler.start_crawl(Region::Na1, "hi", 1).await.is_err());
+            let err = other.start_crawl_v5(Region::Na1, "hi", 1).await.unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::CrawlStopped));
+        });
+        assert_eq!(transport.requests().len(), 1);
+    }
+}
@@ -0,0 +1,19 @@
+//! The library half of the lol-match-crawler. The binaries
+//! (the crawler itself and the mock riot api server) are thin
+//! wrappers around the modules exposed here.
+
+// extern crate definitions
+#[macro_use]
+extern crate error_chain;
+extern crate reqwest;
+extern crate strum;
+#[macro_use]
+extern crate strum_macros;
+extern crate tokio;
+
+// internal mods
+pub mod lol_api;
+pub mod crawler;
+pub mod mock_api;
+pub mod proxy;
+pub mod util;
@@ -0,0 +1,206 @@
+//! A builder for the `Context`, so that optional
+//! configuration (the transport, the host the queries
+//! are sent to, etc.) doesn't have to be threaded through
+//! an ever-growing list of constructor arguments.
+
+// external uses
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize};
+use tokio::time::Duration;
+
+// my mods
+use super::{ApiKey, Context, ContextInner, RetryPolicy};
+use super::cache::DiskCache;
+use super::memory_cache::MemoryCache;
+use super::state::StateFile;
+use super::coordinator::CoordinatorClient;
+use super::endpoint::Service;
+use super::transport::{Transport, HttpTransport, DEFAULT_REQUEST_TIMEOUT};
+
+/// The uri template for the live riot servers. `{host}` is replaced
+/// with the routing value of the query (e.g. `na1`).
+pub const RIOT_BASE_URI : &str = "https://{host}.api.riotgames.com";
+
+/// Collects the configuration for a `Context` and then
+/// constructs it with `build()`.
+///
+/// # Example
+///
+/// ```ignore
+/// let ctx = Context::builder(&key)
+///     .base_uri("http://127.0.0.1:8080")
+///     .build();
+/// ```
+pub struct ContextBuilder {
+    api_keys : Vec<String>,
+    transport : Option<Arc<dyn Transport>>,
+    request_timeout : Duration,
+    base_uri : String,
+    app_rate_limits : Vec<(u64, u64)>,
+    wait_for_capacity : bool,
+    retry_policy : RetryPolicy,
+    disk_cache : Option<DiskCache>,
+    memory_cache : Option<MemoryCache>,
+    state_file : Option<StateFile>,
+    coordinator : Option<CoordinatorClient>,
+}
+
+impl ContextBuilder {
+
+    /// ctor - creates a builder with the default configuration,
+    /// which talks to the live riot servers over http.
+    ///
+    /// # Arguments
+    ///
+    /// `api_key` - the riot api key to send with each query
+    pub fn new(api_key : &str) -> ContextBuilder {
+        ContextBuilder {
+            api_keys : vec![api_key.to_string()],
+            transport : None,
+            request_timeout : DEFAULT_REQUEST_TIMEOUT,
+            base_uri : RIOT_BASE_URI.to_string(),
+            app_rate_limits : Vec::new(),
+            wait_for_capacity : true,
+            retry_policy : RetryPolicy::default(),
+            disk_cache : None,
+            memory_cache : None,
+            state_file : None,
+            coordinator : None,
+        }
+    }
+
+    /// Adds another api key to the pool the queries are spread across.
+    /// Every key has its own application and method rate limits, so
+    /// each query goes out with whichever key has room for it first.
+    pub fn add_api_key(mut self, api_key : &str) -> ContextBuilder {
+        self.api_keys.push(api_key.to_string());
+        self
+    }
+
+    /// Sets the transport queries are sent through.
+    /// Defaults to a new `HttpTransport`.
+    pub fn transport(mut self, transport : Arc<dyn Transport>) -> ContextBuilder {
+        self.transport = Some(transport);
+        self
+    }
+
+    /// Sets how long the default `HttpTransport` waits for a whole
+    /// response before failing the query with `ErrorKind::Timeout`,
+    /// which the `RetryPolicy` then retries like any other timeout.
+    /// Does nothing if `transport` was called. Defaults to
+    /// `DEFAULT_REQUEST_TIMEOUT`.
+    pub fn request_timeout(mut self, timeout : Duration) -> ContextBuilder {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Sets the uri every query is prefixed with. Any `{host}` in the
+    /// template is replaced with the routing value of the query
+    /// (e.g. `na1`), so `"http://127.0.0.1:8080/{host}"` keeps the regions apart
+    /// on a single local server, while `"http://127.0.0.1:8080"` sends
+    /// every region to the same place. Defaults to `RIOT_BASE_URI`.
+    pub fn base_uri(mut self, base_uri : &str) -> ContextBuilder {
+        self.base_uri = base_uri.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sets the application rate limits of each api key as pairs of
+    /// (limit, window in seconds), e.g. `&[(20, 1), (100, 120)]` for a
+    /// development key. Knowing them up front lets concurrent queries
+    /// go out before the first response comes back. Defaults to none,
+    /// in which case they're learned from the first response.
+    pub fn app_rate_limits(mut self, limits : &[(u64, u64)]) -> ContextBuilder {
+        self.app_rate_limits = limits.to_vec();
+        self
+    }
+
+    /// Sets whether queries wait until the rate limits have room for
+    /// them. If not, they fail right away with `ErrorKind::EndpointNotReady`,
+    /// whose `retry_time` is how long to wait. Defaults to true.
+    pub fn wait_for_capacity(mut self, wait_for_capacity : bool) -> ContextBuilder {
+        self.wait_for_capacity = wait_for_capacity;
+        self
+    }
+
+    /// Sets how the `query_*` methods retry failed queries.
+    /// Defaults to `RetryPolicy::default()`.
+    pub fn retry_policy(mut self, retry_policy : RetryPolicy) -> ContextBuilder {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Caches the responses of the queries whose data never changes
+    /// (finished matches and their timelines) under the given directory,
+    /// so later contexts using the same directory (e.g. when the crawl is
+    /// rerun) never query them again. Defaults to no cache.
+    pub fn disk_cache<P : AsRef<Path>>(mut self, dir : P) -> ContextBuilder {
+        self.disk_cache = Some(DiskCache::new(dir));
+        self
+    }
+
+    /// Keeps up to `capacity` recent responses in memory, so looking the
+    /// same data up again doesn't spend any rate limit. Summoners are cached
+    /// for an hour and matchlists for five minutes, which `memory_cache_ttl`
+    /// changes. Defaults to no cache.
+    pub fn memory_cache(mut self, capacity : usize) -> ContextBuilder {
+        self.memory_cache = Some(MemoryCache::new(capacity));
+        self
+    }
+
+    /// Sets how long the responses of a method are kept in the in-memory
+    /// cache, e.g. `.memory_cache_ttl(Service::SummonerV4, summoner_v4::Method::ByName as u32, ttl)`.
+    /// A zero ttl stops the method from being cached. Does nothing unless
+    /// `memory_cache` was called first.
+    pub fn memory_cache_ttl(mut self, service : Service, method : u32, ttl : Duration) -> ContextBuilder {
+        if let Some(memory_cache) = self.memory_cache.as_mut() {
+            memory_cache.set_ttl(service, method, ttl);
+        }
+        self
+    }
+
+    /// Saves the rate-limit state of every api key to the given file
+    /// when the context is dropped, and restores whatever state is
+    /// already in it, so a restarted process doesn't run into windows
+    /// the last one filled up. Windows which ended in between are aged out.
+    /// Defaults to no state file.
+    pub fn state_file<P : AsRef<Path>>(mut self, path : P) -> ContextBuilder {
+        self.state_file = Some(StateFile::new(path));
+        self
+    }
+
+    /// Reserves every query with the rate-limit coordinator listening on
+    /// the unix socket at the given path (see `coordinator::spawn`) before
+    /// sending it, so every process on the machine using the same coordinator
+    /// stays under the rate limits together. Defaults to no coordinator.
+    pub fn coordinator<P : AsRef<Path>>(mut self, path : P) -> ContextBuilder {
+        self.coordinator = Some(CoordinatorClient::new(path));
+        self
+    }
+
+    /// Consumes the builder and constructs the context
+    pub fn build(self) -> Context {
+        let request_timeout = self.request_timeout;
+        let transport = self.transport.unwrap_or_else(|| Arc::new(HttpTransport::with_timeout(request_timeout)));
+        let saved_state = self.state_file.as_ref().map(|state_file| state_file.load()).unwrap_or_default();
+        Context {
+            inner : Arc::new(
+                ContextInner {
+                    keys : self.api_keys.iter().map(|key| ApiKey::new(key, saved_state.endpoints(key))).collect(),
+                    next_key : AtomicUsize::new(0),
+                    transport,
+                    base_uri : self.base_uri,
+                    app_rate_limits : self.app_rate_limits,
+                    wait_for_capacity : self.wait_for_capacity,
+                    retry_policy : self.retry_policy,
+                    disk_cache : self.disk_cache,
+                    memory_cache : self.memory_cache,
+                    in_flight : std::sync::Mutex::new(HashMap::new()),
+                    next_flight_id : AtomicU64::new(0),
+                    state_file : self.state_file,
+                    coordinator : self.coordinator,
+                }),
+        }
+    }
+}
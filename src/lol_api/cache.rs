@@ -0,0 +1,144 @@
+//! An optional on-disk cache of raw response bodies for
+//! the queries whose data never changes once it exists
+//! (e.g. finished matches and their timelines), so rerunning
+//! a crawl doesn't cost any queries for the matches it already has.
+//!
+//! Each body is stored as its own file under the cache directory,
+//! at the path of the query's uri, inside a directory named after
+//! the routing value of its host, e.g.
+//! `<dir>/na1/lol/match/v4/matches/1234.json`. Any character of a
+//! segment besides letters, digits, `-` and `_` is percent-escaped
+//! (e.g. `?` becomes `%3F`), so every uri gets a file of its own.
+
+// external uses
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+// my mods
+use super::{Host, Result};
+
+/// A directory of cached response bodies
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir : PathBuf,
+}
+
+impl DiskCache {
+
+    /// ctor - creates a cache which keeps its files under the
+    /// given directory. The directory is created on the first write.
+    pub fn new<P : AsRef<Path>>(dir : P) -> DiskCache {
+        DiskCache {
+            dir : dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Gets the file a response body is cached in
+    ///
+    /// # Arguments
+    ///
+    /// `host` - the host the query is sent to
+    /// `path` - the path (and query) of the query's uri, e.g. `/lol/match/v4/matches/1234`
+    fn file_path(&self, host : Host, path : &str) -> PathBuf {
+        let mut file_path = self.dir.join(host.host());
+        let path = path.strip_prefix('/').unwrap_or(path);
+        for segment in path.split('/') {
+            file_path.push(Self::escape_segment(segment));
+        }
+        file_path.set_extension("json");
+        file_path
+    }
+
+    /// Escapes a segment of a uri's path into a plain file name, so no uri
+    /// can escape the cache directory and no two uris share a file. Every
+    /// byte besides letters, digits, `-` and `_` becomes `%XX`, and an empty
+    /// segment becomes a lone `%`, which no escaped segment can be.
+    fn escape_segment(segment : &str) -> String {
+        if segment.is_empty() {
+            return "%".to_string();
+        }
+        segment.bytes()
+            .map(|b| if b.is_ascii_alphanumeric() || b == b'-' || b == b'_' { (b as char).to_string() } else { format!("%{:02X}", b) })
+            .collect()
+    }
+
+    /// Gets a cached response body
+    ///
+    /// # Return
+    ///
+    /// The body, or `None` if it isn't cached (or can't be read)
+    pub async fn get(&self, host : Host, path : &str) -> Option<Vec<u8>> {
+        fs::read(self.file_path(host, path)).await.ok()
+    }
+
+    /// Caches a response body, replacing any previous body for the same query
+    ///
+    /// # Remarks
+    ///
+    /// The body is written to a temporary file which is then moved into place,
+    /// so concurrent readers (e.g. other crawlers) never see a partial body.
+    pub async fn put(&self, host : Host, path : &str, body : &[u8]) -> Result<()> {
+        let file_path = self.file_path(host, path);
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let tmp_path = file_path.with_extension(format!("json.{}.tmp", rand::random::<u64>()));
+        fs::write(&tmp_path, body).await?;
+        fs::rename(&tmp_path, &file_path).await?;
+        Ok(())
+    }
+
+    /// Removes a cached response body, e.g. one which turned out to be bad
+    pub async fn remove(&self, host : Host, path : &str) -> Result<()> {
+        fs::remove_file(self.file_path(host, path)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::DiskCache;
+    use crate::lol_api::{Host, Region, RegionalRoute};
+    use tokio::runtime::Runtime;
+
+    #[test]
+    fn test_file_paths_stay_in_cache() {
+        let cache = DiskCache::new("/tmp/cache");
+        assert_eq!(cache.file_path(Host::Platform(Region::Na1), "/lol/match/v4/matches/1234").to_str(),
+                   Some("/tmp/cache/na1/lol/match/v4/matches/1234.json"));
+        assert_eq!(cache.file_path(Host::Regional(RegionalRoute::Americas), "/lol/match/v5/matches/NA1_1").to_str(),
+                   Some("/tmp/cache/americas/lol/match/v5/matches/NA1_1.json"));
+        assert!(cache.file_path(Host::Platform(Region::Na1), "/../../etc/passwd").starts_with("/tmp/cache/na1"));
+        assert_eq!(cache.file_path(Host::Platform(Region::Na1), "/lol/matches?a=1&b=2").to_str(),
+                   Some("/tmp/cache/na1/lol/matches%3Fa%3D1%26b%3D2.json"));
+    }
+
+    #[test]
+    fn test_file_paths_never_collide() {
+        let cache = DiskCache::new("/tmp/cache");
+        let host = Host::Platform(Region::Na1);
+        let paths = ["/a/b", "/a_b", "/a?b", "/a&b", "/a.b", "/a%2Fb", "/a//b", "/a/b/", "/a/b.json", "a/b"];
+        let mut file_paths : Vec<_> = paths.iter().map(|path| cache.file_path(host, path)).collect();
+        file_paths.sort();
+        file_paths.dedup();
+        // a path with or without the leading slash is the same uri
+        assert_eq!(file_paths.len(), paths.len() - 1);
+    }
+
+    #[test]
+    fn test_put_then_get() {
+        let mut rt = Runtime::new().unwrap();
+        let dir = std::env::temp_dir().join(format!("lol-cache-test-{}", rand::random::<u64>()));
+        let cache = DiskCache::new(&dir);
+
+        rt.block_on(async {
+            let host = Host::Platform(Region::Euw1);
+            assert!(cache.get(host, "/lol/match/v4/matches/1").await.is_none());
+            cache.put(host, "/lol/match/v4/matches/1", b"{}").await.unwrap();
+            assert_eq!(cache.get(host, "/lol/match/v4/matches/1").await, Some(b"{}".to_vec()));
+            assert!(cache.get(Host::Platform(Region::Na1), "/lol/match/v4/matches/1").await.is_none());
+        });
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,424 @@
+//! Coordinates the rate limits of every context on a machine,
+//! so several crawler processes sharing an api key stay under
+//! its limits together rather than each filling the buckets on
+//! its own.
+//!
+//! The coordinator is a small daemon (see the `rate_limit_coordinator`
+//! binary) listening on a unix socket. A context pointed at it with
+//! `ContextBuilder::coordinator` asks it to reserve a slot in every
+//! bucket of a query's endpoints before sending the query, and passes
+//! on the counts from the response headers afterwards. The coordinator
+//! keeps its own endpoints for every api key (by fingerprint, so it
+//! never sees the keys themselves), which are the ones all the contexts
+//! have to fit in, while each context keeps tracking its own queries
+//! in flight, cooldowns, etc. as usual.
+//!
+//! Requests and replies are single lines of JSON over the socket. Only
+//! the user the coordinator runs as may connect to it, so other users
+//! on the machine can neither spend the rate limits nor learn them.
+
+// external uses
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::time::Duration;
+
+// my mods
+use super::{Error, ErrorKind, Result, ResultExt};
+use super::endpoint::{Endpoint, Id};
+
+/// A request from a context to the coordinator
+#[derive(Debug, Serialize, Deserialize)]
+enum Request {
+    Reserve { key : u64, endpoints : Vec<(Id, Vec<(u64, u64)>)> },                 // the endpoints of a query, with the limits the context knows
+    Update { key : u64, id : Id, limits : Vec<(u64, u64)>, counts : Vec<(u64, u64)> },  // the limits and counts from a response's headers
+    Release { key : u64, endpoints : Vec<Id> },                                     // the endpoints of a reserved query which wasn't sent after all
+}
+
+/// The coordinator's reply to a `Request`
+#[derive(Debug, Serialize, Deserialize)]
+enum Reply {
+    Reserved,               // the query was counted and can be sent
+    Wait { millis : u64 },  // there's no room for the query yet, so check back after this long
+    Updated,
+    Released,
+}
+
+/// How long a context waits on the coordinator before giving up on
+/// a request. The coordinator is local, so anything close to this
+/// long means it's hung rather than busy.
+const REQUEST_TIMEOUT : Duration = Duration::from_secs(2);
+
+/// How many idle connections a context keeps open to the coordinator.
+/// Concurrent requests each take their own connection, so this
+/// only bounds how many are kept around between bursts.
+const MAX_IDLE_CONNECTIONS : usize = 8;
+
+/// Where the `rate_limit_coordinator` binary listens by default
+pub const DEFAULT_SOCKET_PATH : &str = "/tmp/lol-rate-limit-coordinator.sock";
+
+/// The endpoints of every api key, by key fingerprint
+type Endpoints = Mutex<HashMap<(u64, Id), Endpoint>>;
+
+/// Binds the coordinator to a unix socket at the given path and
+/// spawns it onto the current tokio runtime. The socket is only
+/// accessible to the current user.
+///
+/// # Remarks
+///
+/// A socket left behind by a coordinator which is gone is replaced,
+/// while a coordinator still listening on it (or anything at the
+/// path which isn't a socket) fails the spawn.
+///
+/// # Arguments
+///
+/// `path` - where to create the socket
+pub async fn spawn<P : AsRef<Path>>(path : P) -> Result<()> {
+    let path = path.as_ref();
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(Error::from(format!("{} already exists and isn't a socket", path.display())));
+        }
+        if UnixStream::connect(path).await.is_ok() {
+            return Err(Error::from(format!("A rate-limit coordinator is already listening at {}", path.display())));
+        }
+        std::fs::remove_file(path)
+            .chain_err(|| format!("Unable to remove the stale socket at {}", path.display()))?;
+    }
+
+    let mut listener = UnixListener::bind(path)
+        .chain_err(|| format!("Unable to bind the rate-limit coordinator to {}", path.display()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .chain_err(|| format!("Unable to restrict access to {}", path.display()))?;
+    let endpoints : Arc<Endpoints> = Arc::new(Mutex::new(HashMap::new()));
+
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(_) => continue,
+            };
+            let endpoints = endpoints.clone();
+            tokio::spawn(async move {
+                // the connection just closes on a bad request
+                let _ = serve_connection(stream, &endpoints).await;
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Answers the requests of a single context until it disconnects
+async fn serve_connection(stream : UnixStream, endpoints : &Endpoints) -> Result<()> {
+    let mut stream = BufReader::new(stream);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if stream.read_line(&mut line).await? == 0 {
+            return Ok(());
+        }
+        let reply = handle_request(serde_json::from_str(&line)?, endpoints);
+        let mut reply = serde_json::to_vec(&reply)?;
+        reply.push(b'\n');
+        stream.write_all(&reply).await?;
+    }
+}
+
+/// Applies a request to the coordinator's endpoints
+fn handle_request(request : Request, endpoints : &Endpoints) -> Reply {
+    let mut endpoints = endpoints.lock().unwrap();
+    match request {
+        Request::Reserve { key, endpoints : query_endpoints } => {
+            let mut wait = None;
+            for (id, limits) in &query_endpoints {
+                let ep = endpoints.entry((key, *id)).or_insert_with(Endpoint::new);
+                if !limits.is_empty() {
+                    let last_update_time = ep.last_update_time();
+                    ep.update_buckets(limits, &[], last_update_time);
+                }
+                wait = wait.max(ep.time_until_ready());
+            }
+            match wait {
+                Some(wait) => Reply::Wait { millis : wait.as_millis() as u64 },
+                None => {
+                    // the coordinator only counts queries, the context tracks them while they're in flight
+                    for (id, _) in &query_endpoints {
+                        let ep = endpoints.get_mut(&(key, *id)).unwrap();
+                        ep.reserve();
+                        ep.release();
+                    }
+                    Reply::Reserved
+                },
+            }
+        },
+        Request::Update { key, id, limits, counts } => {
+            endpoints.entry((key, id)).or_insert_with(Endpoint::new)
+                .update_buckets(&limits, &counts, Utc::now());
+            Reply::Updated
+        },
+        Request::Release { key, endpoints : query_endpoints } => {
+            for id in query_endpoints {
+                if let Some(ep) = endpoints.get_mut(&(key, id)) {
+                    ep.unreserve();
+                }
+            }
+            Reply::Released
+        },
+    }
+}
+
+/// A context's connections to the coordinator. Every request takes
+/// an idle connection (or opens a new one), so concurrent queries
+/// don't wait on each other's round trips.
+#[derive(Debug)]
+pub struct CoordinatorClient {
+    path : PathBuf,
+    timeout : Duration,
+    idle : Mutex<Vec<BufReader<UnixStream>>>,
+}
+
+impl CoordinatorClient {
+
+    /// ctor - creates a client for the coordinator listening on the
+    /// socket at the given path. It connects on the first request.
+    pub fn new<P : AsRef<Path>>(path : P) -> CoordinatorClient {
+        Self::with_timeout(path, REQUEST_TIMEOUT)
+    }
+
+    /// ctor - creates a client which gives up on a request once the
+    /// coordinator hasn't answered it within `timeout`
+    pub fn with_timeout<P : AsRef<Path>>(path : P, timeout : Duration) -> CoordinatorClient {
+        CoordinatorClient {
+            path : path.as_ref().to_path_buf(),
+            timeout,
+            idle : Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Sends a request over an idle connection and waits for the
+    /// reply, connecting first if there's none. The connection goes
+    /// back to the idle ones only once its reply has been read.
+    ///
+    /// # Return
+    ///
+    /// The reply, or a `CoordinatorTimeout` error if the
+    /// coordinator didn't answer in time
+    async fn request(&self, request : &Request) -> Result<Reply> {
+        let mut message = serde_json::to_vec(request)?;
+        message.push(b'\n');
+
+        // a connection which timed out is dropped, since its late reply
+        // would otherwise be read as the reply to the next request
+        let mut connection = self.idle.lock().unwrap().pop();
+        let reply = tokio::time::timeout(self.timeout, self.exchange(&mut connection, &message)).await
+            .map_err(|_| Error::from(ErrorKind::CoordinatorTimeout(self.path.clone())))??;
+
+        if let Some(connection) = connection {
+            let mut idle = self.idle.lock().unwrap();
+            if idle.len() < MAX_IDLE_CONNECTIONS {
+                idle.push(connection);
+            }
+        }
+        Ok(reply)
+    }
+
+    /// Writes a request to the connection and reads the reply,
+    /// retrying once on a new connection if the old one went stale.
+    /// Failing to reach the coordinator is an `ErrorKind::Connection`,
+    /// so a `RetryPolicy` treats it like any other connection failure.
+    async fn exchange(&self, connection : &mut Option<BufReader<UnixStream>>, message : &[u8]) -> Result<Reply> {
+        for attempt in 0..2 {
+            if connection.is_none() {
+                let stream = UnixStream::connect(&self.path).await
+                    .map_err(|e| self.connection_error(e))?;
+                *connection = Some(BufReader::new(stream));
+            }
+            let stream = connection.as_mut().unwrap();
+
+            let mut line = String::new();
+            let sent = match stream.write_all(message).await {
+                Ok(()) => stream.read_line(&mut line).await,
+                Err(e) => Err(e),
+            };
+            match sent {
+                Ok(n) if n > 0 => return Ok(serde_json::from_str(&line)?),
+                // the coordinator restarted (or the connection went stale), so try a new one
+                _ if attempt == 0 => *connection = None,
+                Ok(_) => break,
+                Err(e) => return Err(self.connection_error(e)),
+            }
+        }
+        *connection = None;
+        let closed = std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "The rate-limit coordinator closed the connection");
+        Err(self.connection_error(closed))
+    }
+
+    /// Wraps an io error talking to the coordinator as a connection failure
+    fn connection_error(&self, e : std::io::Error) -> Error {
+        Error::with_chain(e, ErrorKind::Connection(self.path.display().to_string()))
+    }
+
+    /// Asks the coordinator to count a query against every bucket of its endpoints
+    ///
+    /// # Arguments
+    ///
+    /// `key` - the fingerprint of the api key the query is sent with
+    /// `endpoints` - the ids of the query's endpoints, with the limits the context knows for each
+    ///
+    /// # Return
+    ///
+    /// `None` if the query was counted and can be sent right away,
+    /// otherwise how long to wait before asking again
+    pub async fn reserve(&self, key : u64, endpoints : Vec<(Id, Vec<(u64, u64)>)>) -> Result<Option<Duration>> {
+        match self.request(&Request::Reserve { key, endpoints }).await? {
+            Reply::Reserved => Ok(None),
+            Reply::Wait { millis } => Ok(Some(Duration::from_millis(millis))),
+            reply => Err(Error::from(format!("Unexpected reply from the rate-limit coordinator: {:?}", reply))),
+        }
+    }
+
+    /// Takes back a query the coordinator reserved, which the
+    /// context ended up not sending
+    ///
+    /// # Arguments
+    ///
+    /// `key` - the fingerprint of the api key the query was reserved for
+    /// `endpoints` - the ids of the query's endpoints
+    pub async fn release(&self, key : u64, endpoints : Vec<Id>) -> Result<()> {
+        match self.request(&Request::Release { key, endpoints }).await? {
+            Reply::Released => Ok(()),
+            reply => Err(Error::from(format!("Unexpected reply from the rate-limit coordinator: {:?}", reply))),
+        }
+    }
+
+    /// Passes the limits and counts from a response's headers on to the coordinator
+    ///
+    /// # Arguments
+    ///
+    /// `key` - the fingerprint of the api key the query was sent with
+    /// `id` - the id of the endpoint the headers are for
+    /// `limits` - the pairs of (limit, window length) from the headers
+    /// `counts` - the pairs of (count, window length) from the headers
+    pub async fn update(&self, key : u64, id : Id, limits : Vec<(u64, u64)>, counts : Vec<(u64, u64)>) -> Result<()> {
+        match self.request(&Request::Update { key, id, limits, counts }).await? {
+            Reply::Updated => Ok(()),
+            reply => Err(Error::from(format!("Unexpected reply from the rate-limit coordinator: {:?}", reply))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::CoordinatorClient;
+    use crate::lol_api::ErrorKind;
+    use crate::lol_api::endpoint::{Host, Id, Region};
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+    use tokio::net::UnixListener;
+    use tokio::runtime::Runtime;
+    use tokio::time::Duration;
+
+    fn temp_socket_path() -> PathBuf {
+        std::env::temp_dir().join(format!("lol-coordinator-test-{}.sock", rand::random::<u64>()))
+    }
+
+    /// Checks that only the current user can reach the
+    /// coordinator, and that a second one can't take its socket
+    #[test]
+    fn test_socket_is_private() {
+        let mut rt = Runtime::new().unwrap();
+        let path = temp_socket_path();
+        rt.block_on(async {
+            super::spawn(&path).await.unwrap();
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+            assert!(super::spawn(&path).await.is_err());
+        });
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// Checks that a released query gives its room back
+    #[test]
+    fn test_release_frees_the_slot() {
+        let mut rt = Runtime::new().unwrap();
+        let path = temp_socket_path();
+        rt.block_on(async {
+            super::spawn(&path).await.unwrap();
+            let client = CoordinatorClient::new(&path);
+            let id = Id::from_host(Host::Platform(Region::Na1));
+
+            assert_eq!(client.reserve(1, vec![(id, vec![(1, 10)])]).await.unwrap(), None);
+            assert!(client.reserve(1, vec![(id, Vec::new())]).await.unwrap().is_some());
+            client.release(1, vec![id]).await.unwrap();
+            assert_eq!(client.reserve(1, vec![(id, Vec::new())]).await.unwrap(), None);
+        });
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// Checks that concurrent requests each get their own
+    /// connection, which are kept for the requests after them
+    #[test]
+    fn test_concurrent_requests_share_idle_connections() {
+        let mut rt = Runtime::new().unwrap();
+        let path = temp_socket_path();
+        rt.block_on(async {
+            super::spawn(&path).await.unwrap();
+            let client = CoordinatorClient::new(&path);
+            let id = Id::from_host(Host::Platform(Region::Na1));
+
+            let (r1, r2, r3) = futures::join!(
+                client.reserve(1, vec![(id, vec![(100, 10)])]),
+                client.reserve(1, vec![(id, vec![(100, 10)])]),
+                client.reserve(1, vec![(id, vec![(100, 10)])]));
+            assert!(r1.is_ok() && r2.is_ok() && r3.is_ok());
+            assert_eq!(client.idle.lock().unwrap().len(), 3);
+
+            client.release(1, vec![id]).await.unwrap();
+            assert_eq!(client.idle.lock().unwrap().len(), 3);
+        });
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// Checks that a coordinator which isn't running fails the
+    /// request with a connection error the retry policy knows
+    #[test]
+    fn test_missing_coordinator_is_a_connection_error() {
+        let mut rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let client = CoordinatorClient::new(temp_socket_path());
+            let err = client.reserve(1, Vec::new()).await.unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::Connection(_)));
+            assert!(err.can_retry());
+        });
+    }
+
+    /// Checks that a coordinator which never answers fails the
+    /// request with a timeout rather than stalling it for good
+    #[test]
+    fn test_hung_coordinator_times_out() {
+        let mut rt = Runtime::new().unwrap();
+        let path = temp_socket_path();
+        rt.block_on(async {
+            // accepts connections but never reads or answers them
+            let mut listener = UnixListener::bind(&path).unwrap();
+            tokio::spawn(async move {
+                let mut streams = Vec::new();
+                while let Ok((stream, _)) = listener.accept().await {
+                    streams.push(stream);
+                }
+            });
+
+            let client = CoordinatorClient::with_timeout(&path, Duration::from_millis(50));
+            let err = client.reserve(1, Vec::new()).await.unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::CoordinatorTimeout(timed_out) if *timed_out == path));
+            assert!(err.can_retry());
+        });
+        std::fs::remove_file(path).unwrap();
+    }
+}
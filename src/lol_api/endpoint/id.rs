@@ -1,59 +1,191 @@
 //! A module to enumerate available endpoints via enum
-//! and then convert those values to a unique endpoint
+//! and then combine those values into a unique endpoint
 //! identifier so that we can store hierarchical endpoints
 //! in a single flat data structure like a HashMap or other
 //! while maintaining the abstract hierarchy.
 //! 
 //! The hierarchy is such that each 
-//! region has services which have
-//! methods. Ids are laid out such that
-//! the first Num(regions) IDs for
-//! region endpoints, then the next 
-//! Num(services) * Num(Regions) IDs for
-//! service endpoints (one set per region), 
-//! then up to Num(Services) * MAX_METHODS_PER_SERVICE 
-//! for each method endpoint after that (one set per service)
+//! host has services which have
+//! methods. A host is either a platform (a `Region`)
+//! or a regional cluster (a `RegionalRoute`), since newer
+//! services are served from the clusters. Riot keeps separate
+//! application, service and method limits on every host, so
+//! each id holds the host along with the rest of its path in
+//! the hierarchy, e.g. `Id::Method(host, service, method)`.
 //! 
 //! We could have used a tree, but the truthfully this whole thing
 //! is statically defined and only changes when the riot api changes
 //! so I went with the statically-defined "tree-like" representation
 //! to have stronger guarentees of bug-free-"ness" at compile time.
 
+// external uses
+use serde::{Deserialize, Serialize};
+
 /// used to identify region. Can be readily convered into a u32
 /// with the as operator, and is guarenteed to be a safe conversion.
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, EnumIter, EnumCount)]
+/// 
+/// Each region is a riot platform routing value, and converts to
+/// (and parses from) its lowercase hostname prefix, e.g. `"euw1"`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, EnumIter, EnumCount, EnumString, IntoStaticStr, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
 pub enum Region {
     Na1 = 0,
+    Br1,
+    Eun1,
+    Euw1,
+    Jp1,
+    Kr,
+    La1,
+    La2,
+    Oc1,
+    Tr1,
+    Ru,
+    Ph2,
+    Sg2,
+    Th2,
+    Tw2,
+    Vn2,
+}
+
+impl Region {
+
+    /// Gets the routing value used as the hostname prefix for
+    /// this region (e.g. `na1` in `https://na1.api.riotgames.com`)
+    pub fn host(self) -> &'static str {
+        self.into()
+    }
+
+    /// Gets the regional cluster which serves the regional
+    /// services (e.g. match-v5) for this platform
+    pub fn route(self) -> RegionalRoute {
+        match self {
+            Region::Na1 | Region::Br1 | Region::La1 | Region::La2 => RegionalRoute::Americas,
+            Region::Kr | Region::Jp1 => RegionalRoute::Asia,
+            Region::Eun1 | Region::Euw1 | Region::Tr1 | Region::Ru => RegionalRoute::Europe,
+            Region::Oc1 | Region::Ph2 | Region::Sg2 | Region::Th2 | Region::Tw2 | Region::Vn2 => RegionalRoute::Sea,
+        }
+    }
+}
+
+/// used to identify a regional cluster. Can be readily convered into a u32
+/// with the as operator, and is guarenteed to be a safe conversion.
+/// 
+/// Each cluster is a riot regional routing value, and converts to
+/// (and parses from) its lowercase hostname prefix, e.g. `"americas"`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, EnumIter, EnumCount, EnumString, IntoStaticStr, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+pub enum RegionalRoute {
+    Americas = 0,
+    Asia,
+    Europe,
+    Sea,
+}
+
+impl RegionalRoute {
+
+    /// Gets the routing value used as the hostname prefix for
+    /// this cluster (e.g. `americas` in `https://americas.api.riotgames.com`)
+    pub fn host(self) -> &'static str {
+        self.into()
+    }
+}
+
+/// Somewhere a query can be sent: either a platform
+/// or a regional cluster. Each host has its own
+/// application rate limits.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum Host {
+    Platform(Region),
+    Regional(RegionalRoute),
+}
+
+impl Host {
+
+    /// Gets the routing value used as the hostname prefix for this host
+    pub fn host(self) -> &'static str {
+        match self {
+            Host::Platform(region) => region.host(),
+            Host::Regional(route) => route.host(),
+        }
+    }
+}
+
+impl std::str::FromStr for Host {
+    type Err = strum::ParseError;
+
+    /// Parses a routing value, either a platform's (e.g. `"na1"`)
+    /// or a regional cluster's (e.g. `"americas"`)
+    fn from_str(routing_value : &str) -> Result<Host, Self::Err> {
+        match routing_value.parse::<Region>() {
+            Ok(region) => Ok(Host::Platform(region)),
+            Err(_) => routing_value.parse::<RegionalRoute>().map(Host::Regional),
+        }
+    }
 }
 
 
 /// used to identify the service. Can be readily convered into a u32
 /// with the as operator, and is guarenteed to be a safe conversion.
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, EnumIter, EnumCount)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, EnumIter, EnumCount, Serialize, Deserialize)]
 pub enum Service {
     SummonerV4 = 0,
     MatchV4,
+    MatchV5,
+    LeagueV4,
+    SpectatorV4,
+    ChampionMasteryV4,
+    AccountV1,
 }
 
-const MAX_METHODS_PER_SERVICE : usize = 128; //need this since each service has its own methods enum
+impl Service {
+
+    /// Determines whether the service is served from the regional
+    /// clusters (e.g. `americas`) rather than the platforms (e.g. `na1`)
+    pub fn is_regional(self) -> bool {
+        match self {
+            Service::SummonerV4 | Service::MatchV4 | Service::LeagueV4
+            | Service::SpectatorV4 | Service::ChampionMasteryV4 => false,
+            Service::MatchV5 | Service::AccountV1 => true,
+        }
+    }
+
+    /// Gets the host that serves this service for queries about
+    /// the given region
+    /// 
+    /// # Arguments
+    /// 
+    /// `region` - the platform the query is about
+    pub fn host(self, region : Region) -> Host {
+        // account-v1 isn't served from the sea cluster, asia serves those players instead
+        if self == Service::AccountV1 && region.route() == RegionalRoute::Sea {
+            return Host::Regional(RegionalRoute::Asia);
+        }
+        if self.is_regional() { Host::Regional(region.route()) } else { Host::Platform(region) }
+    }
+}
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
-pub struct Id(usize);
+/// Identifies an endpoint in the endpoints HashMap
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum Id {
+    Host(Host),
+    Service(Host, Service),
+    Method(Host, Service, u32),     // the u32 representation of the method, since each service has its own methods enum
+}
 
 impl Id {
 
-    /// converts a `Region` enum value to its id value in the endpoints
+    /// converts a `Host` to its id value in the endpoints
     /// HashMap.
     /// 
     /// # Arguments
     /// 
-    /// region : the `Region` value of the region endpoint
+    /// host : the `Host` value of the host endpoint
     /// 
     /// # Return
     /// 
     /// The Id of the endpoint
-    pub fn from_region(region : Region) -> Self {
-        Self(region as usize)
+    pub fn from_host(host : Host) -> Self {
+        Id::Host(host)
     }
 
     /// converts a `Service` enum value to its id value in the `endpoints`
@@ -61,12 +193,10 @@ impl Id {
     /// 
     /// # Arguments
     /// 
-    /// `region` - the region to which the service belongs to
+    /// `host` - the host serving the service
     /// `service` - the `Service` value of the service endpoint
-    pub fn from_service(region : Region, service : Service) -> Self {
-        let region_idx = region as usize;
-        let service_idx = service as usize;
-        Self(REGION_COUNT + (region_idx * SERVICE_COUNT) + (service_idx))
+    pub fn from_service(host : Host, service : Service) -> Self {
+        Id::Service(host, service)
     }
 
     /// converts a method enum's u32 representation
@@ -77,21 +207,22 @@ impl Id {
     /// we use the u32 representation of the method
     /// since each service has its own methods. E.g.
     /// method 0 is different for the service SummonerV4
-    /// from the method 0 of the League service.
+    /// from the method 0 of the League service. The same
+    /// method on different hosts gets different ids, since
+    /// each host has its own method limits.
     /// 
     /// # Arguments
     /// 
+    /// `host` : the host serving the method
     /// `service` : the service to which this method belongs
     /// `method` : the u32 representation of the method endpoint 
     ///     (e.g. summoner_v4::Method::ByName as u32)
-    pub fn from_method(service : Service, method : u32) -> Self {
-        let service_idx = service as usize;
-        let method_idx = method as usize;
-        Self(REGION_COUNT + (SERVICE_COUNT * REGION_COUNT) + (service_idx * MAX_METHODS_PER_SERVICE) + method_idx)
+    pub fn from_method(host : Host, service : Service, method : u32) -> Self {
+        Id::Method(host, service, method)
     }
 
-    /// Given any arbitrary id type, determines if it is a region
-    /// id
+    /// Given any arbitrary id type, determines if it is a host
+    /// id (i.e. a platform or a regional cluster)
     /// 
     /// # Arguments
     /// 
@@ -99,9 +230,9 @@ impl Id {
     /// 
     /// # Return
     /// 
-    /// True if the id belongs to a region endpoint, false otherwise
-    pub fn is_region(&self) -> bool {
-        self.0 < REGION_COUNT
+    /// True if the id belongs to a host endpoint, false otherwise
+    pub fn is_host(&self) -> bool {
+        matches!(self, Id::Host(_))
     }
 
     /// Given any arbitrary id type, determines if it is a service
@@ -114,9 +245,8 @@ impl Id {
     /// # Return
     /// 
     /// True if the id belongs to a service endpoint, false otherwise
-    #[allow(dead_code)]
     pub fn is_service(&self) -> bool {
-        self.0 > REGION_COUNT && self.0 < (REGION_COUNT + (SERVICE_COUNT * REGION_COUNT))
+        matches!(self, Id::Service(..))
     }
 
     /// Given any arbitrary id type, determines if it is a method
@@ -130,6 +260,63 @@ impl Id {
     /// 
     /// True if the id belongs to a method endpoint, false otherwise
     pub fn is_method(&self) -> bool {
-        self.0 > REGION_COUNT + (SERVICE_COUNT * REGION_COUNT)
+        matches!(self, Id::Method(..))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Id, Host, Region, RegionalRoute, Service};
+    use std::collections::HashSet;
+    use strum::IntoEnumIterator;
+
+    /// Checks that every host, service and method id is unique and
+    /// classified as the right kind of id
+    #[test]
+    fn test_id_layout() {
+
+        let hosts : Vec<Host> = Region::iter().map(Host::Platform)
+            .chain(RegionalRoute::iter().map(Host::Regional))
+            .collect();
+        let mut seen = HashSet::new();
+
+        for host in hosts.iter() {
+            let id = Id::from_host(*host);
+            assert!(id.is_host() && !id.is_service() && !id.is_method());
+            assert!(seen.insert(id));
+
+            for service in Service::iter() {
+                let id = Id::from_service(*host, service);
+                assert!(!id.is_host() && id.is_service() && !id.is_method());
+                assert!(seen.insert(id));
+            }
+        }
+
+        for host in hosts.iter() {
+            for service in Service::iter() {
+                for method in 0..4 {
+                    let id = Id::from_method(*host, service, method);
+                    assert!(!id.is_host() && !id.is_service() && id.is_method());
+                    assert!(seen.insert(id));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_regional_routes() {
+        assert_eq!(Region::Na1.route(), RegionalRoute::Americas);
+        assert_eq!(Region::Euw1.route(), RegionalRoute::Europe);
+        assert_eq!(Region::Kr.route(), RegionalRoute::Asia);
+        assert_eq!(Region::Oc1.route(), RegionalRoute::Sea);
+        assert_eq!(Host::Regional(RegionalRoute::Americas).host(), "americas");
+        assert_eq!(Service::MatchV4.host(Region::Na1), Host::Platform(Region::Na1));
+        assert_eq!(Service::MatchV5.host(Region::Euw1), Host::Regional(RegionalRoute::Europe));
+        assert_eq!(Service::AccountV1.host(Region::Na1), Host::Regional(RegionalRoute::Americas));
+        assert_eq!(Service::AccountV1.host(Region::Oc1), Host::Regional(RegionalRoute::Asia));
+        assert_eq!("euw1".parse::<Host>().unwrap(), Host::Platform(Region::Euw1));
+        assert_eq!("asia".parse::<Host>().unwrap(), Host::Regional(RegionalRoute::Asia));
+        assert!("lol".parse::<Host>().is_err());
+    }
+}
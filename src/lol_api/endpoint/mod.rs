@@ -18,9 +18,15 @@
 //! rate-limited (e.g. a full rate-limit bucket for some
 //! time unit).
 //! 
+//! On top of that, each endpoint counts the requests we've
+//! sent against its buckets as they're sent (rather than
+//! waiting for the response headers), so a query can wait until
+//! every bucket has room instead of finding out with a 429.
+//! 
 
 // external uses
-use chrono::{DateTime,Utc};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tokio::time::{Instant, Duration};
 
@@ -28,7 +34,7 @@ use tokio::time::{Instant, Duration};
 // my mods
 use crate::lol_api::{Error, ErrorKind, Result};
 mod id;
-pub use id::{Region, Service, Id};
+pub use id::{Region, RegionalRoute, Host, Service, Id};
 
 /// The status allows us to keep track of
 /// the latent state of the endpoint based
@@ -46,6 +52,30 @@ pub enum Status {
     JustOffCooldown(Duration),   // State is unkown but we just got off a cooldown of the given duration
 }
 
+/// Which layer of rate limits a 429 TOO MANY REQUESTS came from,
+/// as given by the `X-Rate-Limit-Type` header of the response.
+/// Parses from the header's lowercase value, e.g. `"method"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum RateLimitType {
+    Application,    // the api key's limits on a host
+    Method,         // the api key's limits on a single method
+    Service,        // the service itself is overloaded, regardless of the api key
+}
+
+impl RateLimitType {
+
+    /// Determines whether the endpoint with the given id is
+    /// the one enforcing this layer of limits
+    pub fn applies_to(self, id : Id) -> bool {
+        match self {
+            RateLimitType::Application => id.is_host(),
+            RateLimitType::Method => id.is_method(),
+            RateLimitType::Service => id.is_service(),
+        }
+    }
+}
+
 /// Describes the cooldown when the endpoint is in 
 /// a cooldown state. Note that this is heuristically
 /// the cooldown that we wait before trying again, not
@@ -63,7 +93,7 @@ impl CooldownState {
     /// ctor - creates a new cooldown state with the
     /// given duration. The start time is set
     /// the instant of the structs construction.
-    fn new(duration : Duration)->CooldownState {
+    pub fn new(duration : Duration)->CooldownState {
         CooldownState {
             start : Instant::now(),
             duration : duration,
@@ -96,19 +126,73 @@ impl CooldownState {
 /// for the endpoint. E.g. the bucket could represent
 /// a rate limit window with a duration of 20 seconds,
 /// and in those 20 seconds we can send up to `max_count`
-/// requests before being rate limited. This allows us
-/// to keep track of the most recent count for this bucket
-/// based on the server responses 200 OK header fields.
-/// We also keep track of any potential rollover and
+/// requests before being rate limited. We count our own requests
+/// as we send them, and take the server's count from the 200 OK
+/// header fields whenever it's higher (e.g. another program
+/// shares the key). We also keep track of any potential rollover and
 /// keep estimates of when we believe the most recent
 /// window began.
 #[derive(Debug)]
 struct RateLimitBucket {
-    count : u64,                   // count so far
+    count : u64,                   // count so far, including requests still in flight
     max_count : u64,               // max before rate limiting
-    start_time : DateTime<Utc>, // estimate of the start time based on last rollover in ms
+    window : Duration,             // length of the window
+    start_time : DateTime<Utc>,    // estimate of the start of the current window
 }
 
+impl RateLimitBucket {
+
+    /// ctor - creates an empty bucket
+    fn new(max_count : u64, window : Duration) -> RateLimitBucket {
+        RateLimitBucket {
+            count : 0,
+            max_count,
+            window,
+            start_time : Utc::now(),
+        }
+    }
+
+    /// Gets how long until the current window ends, which is
+    /// zero if it already has
+    fn time_left(&self, now : DateTime<Utc>) -> Duration {
+        let end_time = self.start_time + chrono::Duration::from_std(self.window).unwrap();
+        (end_time - now).to_std().unwrap_or_default()
+    }
+
+    /// Starts a new window if the current one has ended
+    fn roll_window(&mut self, now : DateTime<Utc>) {
+        if self.time_left(now) == Duration::from_secs(0) {
+            self.count = 0;
+            self.start_time = now;
+        }
+    }
+}
+
+/// A copy of an endpoint's rate-limit state which outlives the process,
+/// so every time in it is a wall-clock time (in milliseconds since
+/// the UNIX_EPOCH) rather than an `Instant`. The bucket counts include
+/// the queries still in flight, which were counted when they were
+/// reserved and may well reach the server, but not the in-flight tally
+/// itself, since those queries never finish once the process exits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointSnapshot {
+    buckets : Vec<BucketSnapshot>,
+    cooldown_until : Option<i64>,     // when the cooldown ends, if the endpoint was on one
+    last_update_time : i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BucketSnapshot {
+    count : u64,
+    max_count : u64,
+    window : u64,                     // length of the window in seconds
+    start_time : i64,
+}
+
+/// How often to check back on an endpoint whose limits
+/// we don't know yet, while its probe query is in flight
+const PROBE_POLL_INTERVAL : Duration = Duration::from_millis(50);
+
 /// A single endpoint encapsulates our best guess
 /// of an endpoints state (e.g. rate-limited, down, etc.)
 /// and potentially moves between states just before a query
@@ -117,22 +201,25 @@ struct RateLimitBucket {
 /// actual server state, but represents the state as we've
 /// most recently seen it based on server responses.
 /// 
-/// An endpoint can be a platform endpoint (e.g. na1),
+/// An endpoint can be a host endpoint, either a platform (e.g. na1)
+/// or a regional cluster (e.g. americas),
 /// a service (e.g. Summoner_V4), or a method (e.g. by account).
 /// In this way endpoints can be organized hierarchically.
 /// 
-/// The general usage flow is to call `update_status_pre_query()`
+/// The general usage flow is to call `update_status_pre_query()`,
+/// wait out `time_until_ready()`, `reserve()` the query
 /// and then send the query. 
 /// If the response is 200 OK, parse the headers and update
 /// call `update_buckets` on this struct. Finally,
 /// regardless of the response code, call 
-/// `update_status_from_response_code()` after the server responds.
+/// `update_status_from_response_code()` and `release()` after the server responds.
 /// 
 #[derive(Debug)]
 pub struct Endpoint {
     status : Status,                                    // deduced status of the endpoint
     rate_limit_buckets : HashMap<u64, RateLimitBucket>, // map bucket duration to limit
     last_update_time : DateTime<Utc>,
+    in_flight : u64,                                    // requests sent which haven't been responded to yet
 }
 
 impl Endpoint {
@@ -153,8 +240,82 @@ impl Endpoint {
         Endpoint {
             status : Status::Unkown,
             rate_limit_buckets : HashMap::new(),
-            last_update_time : Utc::now(),
+            last_update_time : Utc.timestamp(0, 0),
+            in_flight : 0,
+        }
+    }
+
+    /// constructs an endpoint whose rate limits are known
+    /// up front (e.g. the application limits of the api key),
+    /// so queries don't have to wait for the first response
+    /// to learn them.
+    /// 
+    /// # Arguments
+    /// 
+    /// `limits` : pairs of (limit, window length in seconds)
+    pub fn with_limits(limits : &[(u64,u64)]) -> Endpoint {
+        let mut endpoint = Endpoint::new();
+        for &(limit, bucket_size) in limits {
+            endpoint.rate_limit_buckets.insert(bucket_size, RateLimitBucket::new(limit, Duration::from_secs(bucket_size)));
+        }
+        endpoint
+    }
+
+    /// Gets the known rate limits of the endpoint as pairs of
+    /// (limit, window length in seconds), which are empty until
+    /// they're learned
+    pub fn limits(&self) -> Vec<(u64, u64)> {
+        self.rate_limit_buckets.iter().map(|(bucket_size, bucket)| (bucket.max_count, *bucket_size)).collect()
+    }
+
+    /// Takes a snapshot of the endpoint's state, e.g. to save it
+    /// before the process exits
+    pub fn snapshot(&self) -> EndpointSnapshot {
+        let cooldown_until = match &self.status {
+            Status::Cooldown(cd_state) => cd_state.time_left()
+                .map(|time_left| (Utc::now() + chrono::Duration::from_std(time_left).unwrap()).timestamp_millis()),
+            _ => None,
+        };
+        EndpointSnapshot {
+            buckets : self.rate_limit_buckets.values()
+                .map(|bucket| BucketSnapshot {
+                    count : bucket.count,
+                    max_count : bucket.max_count,
+                    window : bucket.window.as_secs(),
+                    start_time : bucket.start_time.timestamp_millis(),
+                })
+                .collect(),
+            cooldown_until,
+            last_update_time : self.last_update_time.timestamp_millis(),
+        }
+    }
+
+    /// constructs an endpoint from a snapshot taken by `snapshot`,
+    /// e.g. by a previous run of the process.
+    /// 
+    /// # Remarks
+    /// 
+    /// Windows which ended since the snapshot was taken start over
+    /// empty, and a cooldown which ended is dropped, so only the
+    /// limits still in effect carry over. The status is otherwise `Unkown`,
+    /// but since the limits are known no probe is needed.
+    pub fn from_snapshot(snapshot : &EndpointSnapshot) -> Endpoint {
+        let now = Utc::now();
+        let mut endpoint = Endpoint::new();
+        for saved in &snapshot.buckets {
+            let mut bucket = RateLimitBucket::new(saved.max_count, Duration::from_secs(saved.window));
+            bucket.count = saved.count;
+            bucket.start_time = Utc.timestamp_millis(saved.start_time);
+            bucket.roll_window(now);
+            endpoint.rate_limit_buckets.insert(saved.window, bucket);
         }
+        if let Some(cooldown_until) = snapshot.cooldown_until {
+            if let Ok(time_left) = (Utc.timestamp_millis(cooldown_until) - now).to_std() {
+                endpoint.force_cd(time_left);
+            }
+        }
+        endpoint.last_update_time = Utc.timestamp_millis(snapshot.last_update_time);
+        endpoint
     }
 
     /// Uses the response headers to update the rate limit buckets and cache
@@ -162,11 +323,13 @@ impl Endpoint {
     /// 
     /// #Remarks 
     /// 
-    /// This does not check if the data supplied to this function is newer, but will detect
-    /// if we rolled over, and we'll keep track of that. That said,
-    /// you should ensure that you only call this method if you know
-    /// that the timestamp provided does indeed happen after the
-    /// last time this endpoint was updated (e.g. self.last_update_timestamp_ms())
+    /// Counts only ever go up within a window, since our own count already
+    /// includes requests the server may not have seen yet. A count of 1 means
+    /// the server just started a new window, so we restart ours from now,
+    /// which ends it no earlier than the server's. The new window then holds
+    /// the responding query plus whatever else is still in flight, which
+    /// the server will count in it too once they arrive.
+    /// The responding query must not have been released yet.
     /// 
     /// # Arguments
     /// 
@@ -176,32 +339,99 @@ impl Endpoint {
     ///               the `limits` and `counts` data. Should be an i64 milliseconds since the UNIX_EPOCH
     pub fn update_buckets(&mut self, limits : &[(u64,u64)], counts :  &[(u64,u64)], response_time : DateTime<Utc>) {
 
-        // first just update rate limits
-        self.rate_limit_buckets.clear(); // in the future, only update when required
+        // first just update rate limits, dropping any buckets the server no longer reports
+        self.rate_limit_buckets.retain(|bucket_size, _| limits.iter().any(|(_, size)| size == bucket_size));
         for &(limit, bucket_size) in limits {
 
             let bucket = self.rate_limit_buckets.entry(bucket_size)
-                .or_insert(RateLimitBucket {
-                    count : 0,
-                    max_count : 0,
-                    start_time : Utc::now(),
-                });
+                .or_insert_with(|| RateLimitBucket::new(limit, Duration::from_secs(bucket_size)));
             bucket.max_count = limit;
         }
 
         // set counts for existing buckets... They better exist by now
+        let now = Utc::now();
         for &(count, bucket_size) in counts {
 
-            let bucket = self.rate_limit_buckets.get_mut(&bucket_size).unwrap();
-            if bucket.count > count { //detect rollover
-                bucket.start_time = response_time;
+            if let Some(bucket) = self.rate_limit_buckets.get_mut(&bucket_size) {
+                bucket.roll_window(now);
+                if count == 1 { //detect rollover
+                    bucket.start_time = now;
+                    bucket.count = count + self.in_flight.saturating_sub(1);
+                } else {
+                    bucket.count = bucket.count.max(count);
+                }
             }
-            bucket.count = count;
         }
 
         self.last_update_time = response_time;
     }
 
+    /// Determines how long a query has to wait before this endpoint
+    /// has room for it, i.e. the endpoint is off cooldown and every
+    /// bucket has room once the requests in flight are counted.
+    /// 
+    /// # Remarks
+    /// 
+    /// While we haven't heard back from the endpoint yet and don't
+    /// know its limits, only one query (the probe) is let through at a time.
+    /// 
+    /// # Return
+    /// 
+    /// `None` if a query can be sent right away, otherwise
+    /// how long to wait before checking again
+    pub fn time_until_ready(&mut self) -> Option<Duration> {
+
+        if let Status::Cooldown(cd_state) = &self.status {
+            if let Some(time_left) = cd_state.time_left() {
+                return Some(time_left);
+            }
+        }
+
+        if let Status::Unkown = self.status {
+            if self.rate_limit_buckets.is_empty() && self.in_flight > 0 {
+                return Some(PROBE_POLL_INTERVAL);
+            }
+        }
+
+        let now = Utc::now();
+        self.rate_limit_buckets.values_mut()
+            .filter_map(|bucket| {
+                bucket.roll_window(now);
+                if bucket.count >= bucket.max_count { Some(bucket.time_left(now)) } else { None }
+            })
+            .max()
+    }
+
+    /// Counts a query that's about to be sent against
+    /// every bucket. Only call this once `time_until_ready`
+    /// says there's room, and call `release` once the
+    /// query is responded to.
+    pub fn reserve(&mut self) {
+        let now = Utc::now();
+        for bucket in self.rate_limit_buckets.values_mut() {
+            bucket.roll_window(now);
+            bucket.count += 1;
+        }
+        self.in_flight += 1;
+    }
+
+    /// Takes back a query counted with `reserve` which was never
+    /// sent after all, so it stops taking up room in the buckets.
+    /// A query still in flight has to be released as well.
+    pub fn unreserve(&mut self) {
+        let now = Utc::now();
+        for bucket in self.rate_limit_buckets.values_mut() {
+            bucket.roll_window(now);
+            bucket.count = bucket.count.saturating_sub(1);
+        }
+    }
+
+    /// Marks a query reserved with `reserve` as responded to.
+    /// It stays counted in the buckets until their windows end.
+    pub fn release(&mut self) {
+        self.in_flight = self.in_flight.saturating_sub(1);
+    }
+
     /// Updates endpoint status prior to sending a query.
     /// Currently just checks for an expired cooldown and transitions to just off cooldown
     pub fn update_status_pre_query(&mut self) {
@@ -217,7 +447,7 @@ impl Endpoint {
     pub fn update_status_200(&mut self) {
 
         match &self.status {
-            Status::Normal | Status::Unkown | Status::JustOffCooldown(_) => self.set_status_normal_or_cooldown(),
+            Status::Normal | Status::Unkown | Status::JustOffCooldown(_) => self.status = Status::Normal,
             _ => {}
         }
     }
@@ -265,7 +495,7 @@ impl Endpoint {
     }
 
     pub fn most_likely_cd(&self) -> Option<(u64, Duration)> {
-        self.rate_limit_buckets.iter().map(|(k,v)| (v.max_count - v.count, Duration::from_secs(*k))).min()
+        self.rate_limit_buckets.iter().map(|(k,v)| (v.max_count.saturating_sub(v.count), Duration::from_secs(*k))).min()
     }
 
     pub fn force_cd(&mut self, duration : Duration) {
@@ -282,43 +512,67 @@ impl Endpoint {
         self.last_update_time.clone()
     }
 
-    /// A convenience function that decides whether or not 
-    /// we need to set ourselves to cooldown.
-    /// 
-    /// # Return
-    /// 
-    /// `None` if we don't have to cooldown, 
-    /// or a `Some` containing the cooldown to use for 
-    /// settign the Cooldown(_) status.
-    fn should_cooldown(&self) -> Option<CooldownState> {
-        for (bucket_size, bucket) in self.rate_limit_buckets.iter() {
-            if bucket.count == bucket.max_count {
-                //let approx_cd_time = Utc::now().checked_sub_signed(bucket.start_timestamp);
-                return Some(CooldownState::new(Duration::from_secs(*bucket_size)));
-            }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Endpoint, Status};
+    use chrono::Utc;
+    use tokio::time::Duration;
+
+    #[test]
+    fn test_rollover_counts_queries_in_flight() {
+        let mut endpoint = Endpoint::with_limits(&[(10, 10)]);
+        for _ in 0..5 {
+            endpoint.reserve();
         }
+        endpoint.release();
+        endpoint.release();
+
+        // the server started a new window with the first of the three queries still in flight
+        endpoint.update_buckets(&[(10, 10)], &[(1, 10)], Utc::now());
+        assert_eq!(endpoint.rate_limit_buckets[&10].count, 3);
 
-        None
+        // later counts in the same window only ever raise ours
+        endpoint.update_buckets(&[(10, 10)], &[(2, 10)], Utc::now());
+        assert_eq!(endpoint.rate_limit_buckets[&10].count, 3);
     }
 
-    /// Convenience function that saves some typing because
-    /// after a succesful query, the next state option usually could
-    /// be either the normal state or the cooldown state
-    /// depending on the status of the cached rate limit buckets.
-    /// 
-    // after update, if this was the last in a cd window,
-    // set cooldowns. This will overwrite any existing or temporary
-    // cooldowns set by a 400 status code from a request that
-    // was sent after but returned early
-    fn set_status_normal_or_cooldown(&mut self) {
-
-        if let Some(cd_state) = self.should_cooldown() {
-            println!("should cooldown: {:?}", &cd_state);
-            self.status = Status::Cooldown(cd_state);
-        }
-        else {
-            self.status = Status::Normal;
-        }
+    #[test]
+    fn test_snapshot_restores_open_windows() {
+        let mut endpoint = Endpoint::with_limits(&[(1, 10), (100, 1)]);
+        endpoint.reserve();
+        endpoint.release();
+        let mut restored = Endpoint::from_snapshot(&endpoint.snapshot());
+
+        // the 10 second window is still full, while the 1 second window ends soon after
+        let wait = restored.time_until_ready().unwrap();
+        assert!(wait > Duration::from_secs(9) && wait <= Duration::from_secs(10));
+        assert!(matches!(restored.status(), Status::Unkown));
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_snapshot_ages_out_expired_state() {
+        let mut endpoint = Endpoint::with_limits(&[(1, 1)]);
+        endpoint.reserve();
+        endpoint.force_cd(Duration::from_millis(500));
+        let mut snapshot = endpoint.snapshot();
+
+        // pretend the snapshot was taken two seconds ago
+        for bucket in snapshot.buckets.iter_mut() {
+            bucket.start_time -= 2000;
+        }
+        snapshot.cooldown_until = snapshot.cooldown_until.map(|until| until - 2000);
+        let mut restored = Endpoint::from_snapshot(&snapshot);
+        assert!(restored.time_until_ready().is_none());
+        assert!(matches!(restored.status(), Status::Unkown));
+
+        // while a cooldown that's still running carries over
+        let mut endpoint = Endpoint::new();
+        endpoint.force_cd(Duration::from_secs(5));
+        let restored = Endpoint::from_snapshot(&endpoint.snapshot());
+        assert!(matches!(restored.status(), Status::Cooldown(_)));
+        assert!(restored.last_update_time() <= Utc::now());
+    }
+}
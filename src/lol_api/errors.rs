@@ -5,7 +5,9 @@ error_chain!{
         Reqwest(::reqwest::Error);
         HeaderToString(::reqwest::header::ToStrError);
         Serde(::serde::de::value::Error);
+        Json(::serde_json::Error);
         JoinError(::tokio::task::JoinError);
+        Io(::std::io::Error);
     }
 
     errors {
@@ -13,6 +15,51 @@ error_chain!{
             description("Endpoint is not in a ready state.")
             display("Endpoint in state {:?} is not ready to receive queries.", status)
         }
+
+        RateLimited(limit_type : Option<crate::lol_api::endpoint::RateLimitType>, retry_after : ::tokio::time::Duration) {
+            description("Server responded with 429 TOO MANY REQUESTS.")
+            display("Rate limited by the {:?} limits, retry after {:?}.", limit_type, retry_after)
+        }
+
+        BadRequest(status : ::reqwest::StatusCode, uri : String, body : String) {
+            description("Server responded with 400 BAD REQUEST.")
+            display("Bad request {} ({}): {}", uri, status, body)
+        }
+
+        Unauthorized(status : ::reqwest::StatusCode, uri : String, body : String) {
+            description("Server rejected the api key.")
+            display("Api key was rejected for query {} ({}), it may be invalid or expired: {}", uri, status, body)
+        }
+
+        NotFound(status : ::reqwest::StatusCode, uri : String, body : String) {
+            description("Server responded with 404 NOT FOUND.")
+            display("No data found for query {} ({}): {}", uri, status, body)
+        }
+
+        UnsupportedMediaType(status : ::reqwest::StatusCode, uri : String, body : String) {
+            description("Server responded with 415 UNSUPPORTED MEDIA TYPE.")
+            display("Unsupported media type for query {} ({}): {}", uri, status, body)
+        }
+
+        ResponseStatus(status : ::reqwest::StatusCode) {
+            description("Server responded with an unsuccessful status code.")
+            display("Server responded with status {}.", status)
+        }
+
+        Timeout(uri : String) {
+            description("Server didn't respond in time.")
+            display("Query to {} timed out.", uri)
+        }
+
+        Connection(uri : String) {
+            description("Unable to connect to the server.")
+            display("Unable to connect to the server for query {}.", uri)
+        }
+
+        CoordinatorTimeout(path : ::std::path::PathBuf) {
+            description("Rate-limit coordinator didn't respond in time.")
+            display("The rate-limit coordinator at {} didn't respond in time.", path.display())
+        }
     }
 }
 
@@ -24,8 +71,61 @@ impl From<crate::lol_api::endpoint::Status> for ErrorKind {
 
 impl Error {
 
+    /// Creates the error for an unsuccessful response, picking
+    /// the kind by its status code
+    ///
+    /// # Arguments
+    ///
+    /// `status` - the status code the server responded with
+    /// `uri` - the uri the query was sent to
+    /// `body` - the response body, which for riot errors is a json
+    ///     object like `{"status":{"message":"...","status_code":404}}`
+    pub fn from_status(status : ::reqwest::StatusCode, uri : &str, body : &[u8]) -> Error {
+        let (uri, body) = (uri.to_string(), String::from_utf8_lossy(body).into_owned());
+        let kind = match status {
+            ::reqwest::StatusCode::BAD_REQUEST => ErrorKind::BadRequest(status, uri, body),
+            ::reqwest::StatusCode::UNAUTHORIZED | ::reqwest::StatusCode::FORBIDDEN => ErrorKind::Unauthorized(status, uri, body),
+            ::reqwest::StatusCode::NOT_FOUND => ErrorKind::NotFound(status, uri, body),
+            ::reqwest::StatusCode::UNSUPPORTED_MEDIA_TYPE => ErrorKind::UnsupportedMediaType(status, uri, body),
+            _ => ErrorKind::ResponseStatus(status),
+        };
+        Error::from(kind)
+    }
+
+    /// Gets the status code of the response the error came from,
+    /// if it came from one
+    pub fn status(&self) -> Option<::reqwest::StatusCode> {
+        match self.kind() {
+            ErrorKind::BadRequest(status, _, _) | ErrorKind::Unauthorized(status, _, _)
+            | ErrorKind::NotFound(status, _, _) | ErrorKind::UnsupportedMediaType(status, _, _)
+            | ErrorKind::ResponseStatus(status) => Some(*status),
+            _ => None,
+        }
+    }
+
     pub fn can_retry(&self) -> bool {
-        if self.retry_time().is_some() { true } else { false }
+        self.class().is_some()
+    }
+
+    /// Gets the class of failure a `RetryPolicy` has a rule for,
+    /// or `None` if the error is never worth retrying
+    pub fn class(&self) -> Option<crate::lol_api::ErrorClass> {
+        use crate::lol_api::ErrorClass;
+
+        match self.kind() {
+            ErrorKind::EndpointNotReady(_) | ErrorKind::RateLimited(_, _) => {
+                self.retry_time().map(|_| ErrorClass::RateLimited)
+            },
+            ErrorKind::ResponseStatus(status) => match *status {
+                ::reqwest::StatusCode::INTERNAL_SERVER_ERROR | ::reqwest::StatusCode::BAD_GATEWAY
+                | ::reqwest::StatusCode::SERVICE_UNAVAILABLE | ::reqwest::StatusCode::GATEWAY_TIMEOUT => Some(ErrorClass::ServerError),
+                _ => None,
+            },
+            ErrorKind::Timeout(_) | ErrorKind::CoordinatorTimeout(_) => Some(ErrorClass::Timeout),
+            ErrorKind::Connection(_) => Some(ErrorClass::Connection),
+            ErrorKind::Reqwest(e) if e.is_timeout() => Some(ErrorClass::Timeout),
+            _ => None,
+        }
     }
 
     pub fn retry_time(&self) -> Option<tokio::time::Duration> {
@@ -43,15 +143,10 @@ impl Error {
                 }
             },
 
-            // if 429, then we're rate limited but haven't yet gotten the header
-            // back from the last valid request sent (e.g. header indicates count == limit)
-            // We can retry again certainly at an arbitrary time, though the next will probably
-            // just give us a EndpointNotReady, whereby we can adjust our wait time accordingly
-            // and do a second retry
-            //ErrorKind::Reqwest(err) if err.is_status() => {
-            //    let status = err.status().unwrap();
-            //    if status == reqwest::StatusCode::TOO_MANY_REQUESTS { Some(tokio::time::Duration::from_secs(5)) } else { None }
-            //},
+            // if 429, then we're rate limited and the endpoints were put on
+            // cooldown for either the server's Retry-After or our best guess
+            ErrorKind::RateLimited(_, retry_after) => Some(*retry_after),
+
             _ => None
         }
     }
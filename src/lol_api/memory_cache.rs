@@ -0,0 +1,281 @@
+//! A bounded in-memory cache of recent responses for the
+//! queries whose data changes slowly (e.g. summoners and
+//! matchlists), so looking the same player up again a few
+//! minutes later doesn't spend any of the method's rate limit.
+//!
+//! Only methods with a time to live are cached, which applies to
+//! the method on every host. Responses are keyed by their method's
+//! endpoint id and the full uri of the query (which holds all of its
+//! parameters), and once the cache is full the least recently used
+//! response makes room.
+
+// external uses
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use tokio::time::{Duration, Instant};
+
+// my mods
+use super::endpoint::{Id, Service};
+use super::services::{summoner_v4, match_v4, match_v5};
+use super::transport::RawResponse;
+
+/// How long summoners are cached for by default
+const DEFAULT_SUMMONER_TTL : Duration = Duration::from_secs(60 * 60);
+
+/// How long match histories are cached for by default. Players
+/// finish a game every half hour at most, so anything shorter
+/// than that rarely misses a match.
+const DEFAULT_MATCHLIST_TTL : Duration = Duration::from_secs(5 * 60);
+
+/// Counts of how the cache served the lookups sent through it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits : u64,
+    pub misses : u64,           // including lookups of expired responses
+    pub evictions : u64,        // responses dropped to make room, before they expired
+}
+
+/// A cached response and when it stops being served
+#[derive(Debug)]
+struct Entry {
+    response : RawResponse,
+    expires_at : Instant,
+    stored : u64,               // the tick the entry was stored at, which keeps its place in the expiry order unique
+    last_used : u64,            // the tick the entry was last served or stored at
+}
+
+type Key = (Id, String);
+
+/// The mutable part of the cache, behind its lock
+#[derive(Debug, Default)]
+struct CacheState {
+    entries : HashMap<Key, Entry>,
+    recency : BTreeMap<u64, Key>,   // every entry by its last_used tick, least recently used first
+    expiry : BTreeMap<(Instant, u64), Key>,     // every entry by when it expires, soonest first
+    tick : u64,
+    stats : CacheStats,
+}
+
+impl CacheState {
+
+    /// Moves an entry to the most recently used end
+    fn touch(&mut self, key : &Key) {
+        self.tick += 1;
+        let tick = self.tick;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.recency.remove(&entry.last_used);
+            entry.last_used = tick;
+            self.recency.insert(tick, key.clone());
+        }
+    }
+
+    fn remove(&mut self, key : &Key) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.last_used);
+            self.expiry.remove(&(entry.expires_at, entry.stored));
+        }
+    }
+}
+
+/// The cache itself, which a context shares between all of its queries
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity : usize,
+    ttls : HashMap<(Service, u32), Duration>,     // by service and the u32 representation of the method
+    state : Mutex<CacheState>,
+}
+
+impl MemoryCache {
+
+    /// ctor - creates a cache holding at most `capacity` responses, which
+    /// caches the summoner and matchlist lookups by default
+    pub fn new(capacity : usize) -> MemoryCache {
+        let mut ttls = HashMap::new();
+        for method in [summoner_v4::Method::ByAccount, summoner_v4::Method::ByName,
+                       summoner_v4::Method::ByPuuid, summoner_v4::Method::BySummonerId].iter() {
+            ttls.insert((Service::SummonerV4, *method as u32), DEFAULT_SUMMONER_TTL);
+        }
+        ttls.insert((Service::MatchV4, match_v4::Method::MatchlistByAccount as u32), DEFAULT_MATCHLIST_TTL);
+        ttls.insert((Service::MatchV5, match_v5::Method::MatchIdsByPuuid as u32), DEFAULT_MATCHLIST_TTL);
+
+        MemoryCache {
+            capacity,
+            ttls,
+            state : Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Sets how long a method's responses are served from the cache.
+    /// A zero ttl stops the method from being cached at all.
+    ///
+    /// # Arguments
+    ///
+    /// `service` - the service the method belongs to
+    /// `method` - the u32 representation of the method (e.g. summoner_v4::Method::ByName as u32)
+    /// `ttl` - how long the responses are served for
+    pub fn set_ttl(&mut self, service : Service, method : u32, ttl : Duration) {
+        if ttl == Duration::from_secs(0) {
+            self.ttls.remove(&(service, method));
+        } else {
+            self.ttls.insert((service, method), ttl);
+        }
+    }
+
+    /// Gets how long the responses of the method with the given id are cached for
+    fn ttl(&self, method_id : Id) -> Option<Duration> {
+        match method_id {
+            Id::Method(_, service, method) => self.ttls.get(&(service, method)).copied(),
+            _ => None,
+        }
+    }
+
+    /// Checks whether a method's responses are cached
+    pub fn caches(&self, method_id : Id) -> bool {
+        self.ttl(method_id).is_some()
+    }
+
+    /// Gets the cached response to a query, if it hasn't expired
+    ///
+    /// # Arguments
+    ///
+    /// `method_id` - the endpoint id of the query's method
+    /// `uri` - the full uri of the query
+    pub fn get(&self, method_id : Id, uri : &str) -> Option<RawResponse> {
+        let mut state = self.state.lock().unwrap();
+        let key = (method_id, uri.to_string());
+
+        let response = match state.entries.get(&key) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(entry.response.clone()),
+            Some(_) => {
+                state.remove(&key);
+                None
+            },
+            None => None,
+        };
+        match response {
+            Some(_) => {
+                state.stats.hits += 1;
+                state.touch(&key);
+            },
+            None => state.stats.misses += 1,
+        }
+        response
+    }
+
+    /// Caches the response to a query, evicting the least recently
+    /// used responses if the cache is full. Does nothing if the
+    /// query's method isn't cached.
+    pub fn put(&self, method_id : Id, uri : &str, response : &RawResponse) {
+        let ttl = match self.ttl(method_id) {
+            Some(ttl) if self.capacity > 0 => ttl,
+            _ => return,
+        };
+        let mut state = self.state.lock().unwrap();
+        let key = (method_id, uri.to_string());
+        state.remove(&key);
+
+        let now = Instant::now();
+        while state.entries.len() >= self.capacity {
+            // expired responses make room first, since they'd never be served again anyway
+            let expired = state.expiry.iter().next()
+                .filter(|((expires_at, _), _)| *expires_at <= now)
+                .map(|(_, key)| key.clone());
+            match expired {
+                Some(expired) => state.remove(&expired),
+                None => {
+                    let lru = state.recency.values().next().cloned().unwrap();
+                    state.remove(&lru);
+                    state.stats.evictions += 1;
+                },
+            }
+        }
+
+        state.tick += 1;
+        let stored = state.tick;
+        let expires_at = now + ttl;
+        state.expiry.insert((expires_at, stored), key.clone());
+        state.entries.insert(key.clone(), Entry { response : response.clone(), expires_at, stored, last_used : 0 });
+        state.touch(&key);
+    }
+
+    /// Gets the hit, miss and eviction counts so far
+    pub fn stats(&self) -> CacheStats {
+        self.state.lock().unwrap().stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{MemoryCache, CacheStats};
+    use crate::lol_api::endpoint::{Id, Host, Region, Service};
+    use crate::lol_api::services::{summoner_v4, match_v4};
+    use crate::lol_api::transport::RawResponse;
+    use reqwest::StatusCode;
+    use reqwest::header::HeaderMap;
+    use tokio::time::Duration;
+
+    fn response(body : &str) -> RawResponse {
+        RawResponse::new(StatusCode::OK, HeaderMap::new(), body.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let cache = MemoryCache::new(2);
+        let by_name = Id::from_method(Host::Platform(Region::Na1), Service::SummonerV4, summoner_v4::Method::ByName as u32);
+
+        cache.put(by_name, "a", &response("a"));
+        cache.put(by_name, "b", &response("b"));
+        assert!(cache.get(by_name, "a").is_some());     // b is now the least recently used
+        cache.put(by_name, "c", &response("c"));
+
+        assert!(cache.get(by_name, "b").is_none());
+        assert_eq!(cache.get(by_name, "a").unwrap().body(), b"a");
+        assert_eq!(cache.get(by_name, "c").unwrap().body(), b"c");
+        assert_eq!(cache.stats(), CacheStats { hits : 3, misses : 1, evictions : 1 });
+    }
+
+    #[test]
+    fn test_expired_evicted_first() {
+        let mut cache = MemoryCache::new(2);
+        let by_name = Id::from_method(Host::Platform(Region::Na1), Service::SummonerV4, summoner_v4::Method::ByName as u32);
+        let matchlist = Id::from_method(Host::Platform(Region::Na1), Service::MatchV4, match_v4::Method::MatchlistByAccount as u32);
+        cache.set_ttl(Service::MatchV4, match_v4::Method::MatchlistByAccount as u32, Duration::from_millis(20));
+
+        cache.put(by_name, "a", &response("a"));
+        cache.put(matchlist, "l", &response("l"));
+        std::thread::sleep(Duration::from_millis(30));
+
+        // the expired matchlist makes room, even though the summoner is the least recently used
+        cache.put(by_name, "b", &response("b"));
+        assert!(cache.get(by_name, "a").is_some());
+        assert!(cache.get(by_name, "b").is_some());
+        assert_eq!(cache.stats().evictions, 0);
+    }
+
+    #[test]
+    fn test_ttls() {
+        let mut cache = MemoryCache::new(10);
+        let matchlist = Id::from_method(Host::Platform(Region::Na1), Service::MatchV4, match_v4::Method::MatchlistByAccount as u32);
+        let match_by_id = Id::from_method(Host::Platform(Region::Na1), Service::MatchV4, match_v4::Method::MatchById as u32);
+        assert!(cache.caches(matchlist));
+        assert!(!cache.caches(match_by_id));
+
+        // methods without a ttl are never stored
+        cache.put(match_by_id, "m", &response("m"));
+        assert!(cache.get(match_by_id, "m").is_none());
+
+        cache.set_ttl(Service::MatchV4, match_v4::Method::MatchlistByAccount as u32, Duration::from_millis(50));
+        cache.put(matchlist, "l", &response("l"));
+        assert!(cache.get(matchlist, "l").is_some());
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(cache.get(matchlist, "l").is_none());
+
+        // the ttl is the method's on every host
+        let euw_matchlist = Id::from_method(Host::Platform(Region::Euw1), Service::MatchV4, match_v4::Method::MatchlistByAccount as u32);
+        assert!(cache.caches(euw_matchlist));
+
+        cache.set_ttl(Service::MatchV4, match_v4::Method::MatchlistByAccount as u32, Duration::from_secs(0));
+        assert!(!cache.caches(matchlist) && !cache.caches(euw_matchlist));
+    }
+}
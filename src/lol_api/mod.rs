@@ -10,24 +10,52 @@
 
 // external uses
 use chrono::{DateTime, Utc};
-use reqwest::{Client, Response};
+use futures::channel::oneshot;
+use futures::future::{BoxFuture, FutureExt, Shared};
+use futures::stream::{self, Stream};
 use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use tokio::sync::Mutex;
 
 // my mods/uses
 mod services;
 mod endpoint;
 mod errors;
+mod builder;
+mod paging;
+mod retry;
+mod cache;
+mod memory_cache;
+mod state;
+pub mod coordinator;
+pub mod transport;
 
 pub use errors::*;
-pub use endpoint::{Region, Service};
+pub use builder::{ContextBuilder, RIOT_BASE_URI};
+pub use endpoint::{Region, RegionalRoute, Host, Service, RateLimitType};
+pub use retry::{RetryPolicy, ErrorClass};
+pub use memory_cache::CacheStats;
 pub use services::summoner_v4::SummonerDto;
-pub use services::match_v4::{MatchDto, MatchlistDto, MatchReferenceDto, PlayerDto, ParticipantIdentityDto, ParticipantStatsDto, ParticipantTimelineDto};
-
-use services::{summoner_v4, match_v4};
+pub use services::match_v4::{MatchDto, MatchlistDto, MatchlistFilter, MatchReferenceDto, PlayerDto, ParticipantIdentityDto, ParticipantStatsDto, ParticipantTimelineDto};
+pub use services::match_v4::{MatchTimelineDto, MatchFrameDto, MatchParticipantFrameDto, MatchPositionDto, MatchEventDto};
+pub use services::match_v4;
+pub use services::match_v5;
+pub use services::league_v4;
+pub use services::spectator_v4;
+pub use services::champion_mastery_v4;
+pub use services::account_v1;
+
+pub use services::summoner_v4;
 use endpoint::{Endpoint, Id};
+use transport::{Transport, RawResponse};
+use paging::MatchlistPager;
+use cache::DiskCache;
+use memory_cache::MemoryCache;
+use state::StateFile;
+use coordinator::CoordinatorClient;
 
 /// The context we construct to guess the state
 /// of the various endpoints within the league of legends
@@ -36,9 +64,117 @@ use endpoint::{Endpoint, Id};
 /// of rate limits and such.
 #[derive(Debug)]
 struct ContextInner {
+    keys : Vec<ApiKey>,                         // the pool of api keys the queries are spread across
+    next_key : AtomicUsize,                     // where the next query starts looking for a key with room, so the keys take turns
+    transport : Arc<dyn Transport>,
+    base_uri : String,
+    app_rate_limits : Vec<(u64, u64)>,          // known application limits as (limit, window in seconds), learned from responses if empty
+    wait_for_capacity : bool,                   // wait for room in the rate limits, rather than failing with EndpointNotReady
+    retry_policy : RetryPolicy,                 // how the query_* methods retry failed queries
+    disk_cache : Option<DiskCache>,             // where the bodies of immutable data (e.g. matches) are cached, if anywhere
+    memory_cache : Option<MemoryCache>,         // recent responses of slowly changing data (e.g. summoners), if cached
+    in_flight : std::sync::Mutex<HashMap<String, (u64, InFlightQuery)>>,   // queries being sent by uri, for identical queries to wait on
+    next_flight_id : AtomicU64,
+    state_file : Option<StateFile>,             // where the rate-limit state is saved when the context is dropped, if anywhere
+    coordinator : Option<CoordinatorClient>,    // reserves every query with the other processes on the machine, if set
+}
+
+impl ContextInner {
+
+    /// Saves the rate-limit state of every key to the state file, keeping
+    /// the state of any other keys already in it. Keys whose state is
+    /// locked by a query (which can only happen while queries are running)
+    /// keep whatever was saved for them before.
+    fn save_state(&self) -> Result<()> {
+        let state_file = match &self.state_file {
+            Some(state_file) => state_file,
+            None => return Ok(()),
+        };
+        let mut state = state_file.load();
+        for key in &self.keys {
+            if let Ok(endpoints) = key.endpoints.try_lock() {
+                state.set_endpoints(&key.key, &endpoints);
+            }
+        }
+        state_file.save(&state)
+    }
+}
+
+impl Drop for ContextInner {
+    fn drop(&mut self) {
+        if let Err(e) = self.save_state() {
+            eprintln!("Unable to save the rate-limit state: {}", e);
+        }
+    }
+}
+
+/// An api key along with its own rate-limit state. Riot keeps
+/// separate application and method limits for every key, so
+/// each key tracks its own endpoints.
+#[derive(Debug)]
+struct ApiKey {
+    key : String,
+    fingerprint : u64,                          // identifies the key to the coordinator
     endpoints : Mutex<HashMap<Id, Endpoint>>,  // now the whole struct is sync, hurray!
-    api_key : String,
-    client : Client
+}
+
+impl ApiKey {
+
+    /// ctor - creates a key with the given rate-limit state,
+    /// e.g. restored from a state file (or empty)
+    fn new(key : &str, endpoints : HashMap<Id, Endpoint>) -> ApiKey {
+        ApiKey {
+            key : key.to_string(),
+            fingerprint : state::fingerprint(key),
+            endpoints : Mutex::new(endpoints),
+        }
+    }
+}
+
+/// How a query in flight turned out, shared by every identical
+/// query waiting on it. `None` if the query was dropped before it finished.
+type InFlightQuery = Shared<BoxFuture<'static, Option<FlightOutcome>>>;
+
+#[derive(Debug, Clone)]
+enum FlightOutcome {
+    Response(RawResponse),          // a successful response
+    Status(StatusCode, String),     // an unsuccessful response's status and body
+    Failed,                         // any other failure, which each query finds out about for itself
+}
+
+impl FlightOutcome {
+
+    fn of(result : &Result<RawResponse>) -> FlightOutcome {
+        let e = match result {
+            Ok(response) => return FlightOutcome::Response(response.clone()),
+            Err(e) => e,
+        };
+        match e.kind() {
+            ErrorKind::BadRequest(status, _, body) | ErrorKind::Unauthorized(status, _, body)
+            | ErrorKind::NotFound(status, _, body) | ErrorKind::UnsupportedMediaType(status, _, body) => {
+                FlightOutcome::Status(*status, body.clone())
+            },
+            ErrorKind::ResponseStatus(status) => FlightOutcome::Status(*status, String::new()),
+            _ => FlightOutcome::Failed,
+        }
+    }
+}
+
+/// Takes a query out of flight once the query which sent it is done
+struct InFlightGuard<'a> {
+    inner : Arc<ContextInner>,
+    uri : &'a str,
+    flight_id : u64,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self.inner.in_flight.lock().unwrap();
+        // an identical query may have been put in flight since, which isn't ours to take out
+        if in_flight.get(self.uri).map(|(flight_id, _)| *flight_id) == Some(self.flight_id) {
+            in_flight.remove(self.uri);
+        }
+    }
 }
 
 pub struct Context {
@@ -48,23 +184,84 @@ pub struct Context {
 impl Context {
 
     pub fn new(api_key : &str) -> Context {
-        Context{ 
-            inner : Arc::new(
-                ContextInner{
-                    endpoints : Mutex::new(HashMap::new()),
-                    api_key : api_key.to_string(),
-                    client : Client::new(),
-                }),
-        }
+        Self::builder(api_key).build()
+    }
+
+    /// Creates a `ContextBuilder` for configuring a context
+    /// beyond the defaults used by `new()`
+    /// 
+    /// # Arguments
+    /// 
+    /// `api_key` - the riot api key to send with each query
+    pub fn builder(api_key : &str) -> ContextBuilder {
+        ContextBuilder::new(api_key)
+    }
+
+    /// ctor - creates a context that sends all of its queries
+    /// through the given transport instead of the default
+    /// `HttpTransport`. Mostly useful for testing with a
+    /// `transport::MockTransport`.
+    /// 
+    /// # Arguments
+    /// 
+    /// `api_key` - the riot api key to send with each query
+    /// `transport` - the transport to send queries through
+    pub fn with_transport(api_key : &str, transport : Arc<dyn Transport>) -> Context {
+        Self::builder(api_key).transport(transport).build()
+    }
+
+    /// Gets how the in-memory cache served the queries so far.
+    /// The counts are all zero if the context has no cache.
+    pub fn memory_cache_stats(&self) -> CacheStats {
+        self.inner.memory_cache.as_ref().map(MemoryCache::stats).unwrap_or_default()
+    }
+
+    /* RAW QUERIES */
+
+    /// Sends a query for any riot api path and gives back the raw response
+    /// rather than parsing it, e.g. to pass it on as the proxy does. The query
+    /// is rate limited, cached and retried just like the typed queries.
+    /// 
+    /// # Arguments
+    /// 
+    /// `host` - the host to send the query to, which has to serve the service
+    /// `service` - the service the method belongs to
+    /// `method` - the u32 representation of the method (e.g. summoner_v4::Method::ByName as u32)
+    /// `path` - the path and query of the uri, e.g. `/lol/summoner/v4/summoners/by-name/hi`
+    pub async fn query_raw(
+        &self, host : Host, service : Service, method : u32, path : &str)->Result<RawResponse> {
+
+        let inner = self.inner.clone();
+        let path_str = path.to_string();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_raw(inner.clone(), host, service, method, path_str.clone())
+            }).await
+    }
+
+    pub async fn try_query_raw(
+        &self, host : Host, service : Service, method : u32, path : &str)->Result<RawResponse> {
+
+        Self::_try_query_raw(self.inner.clone(), host, service, method, path.to_string()).await
+    }
+
+    async fn _try_query_raw(
+        inner : Arc<ContextInner>, host : Host, service : Service, method : u32, path : String)->Result<RawResponse> {
+
+        let uri = Self::host_uri(&inner, host) + &path;
+        let endpoint_ids = [Id::from_host(host),
+                            Id::from_service(host, service),
+                            Id::from_method(host, service, method)];
+        Self::send_query(inner.clone(), &uri, &endpoint_ids).await
     }
 
     /** SUMMONER V4 METHODS */
     pub async fn query_summoner_v4_by_summoner_name(
-        &self, region : Region, summoner_name : &str, retry_count : usize)->Result<summoner_v4::SummonerDto>{
+        &self, region : Region, summoner_name : &str)->Result<summoner_v4::SummonerDto>{
 
         let inner = self.inner.clone();
         let name_str = summoner_name.to_string();
-        Self::query_with_retry(retry_count,
+        Self::query_with_retry(&self.inner.retry_policy,
             move || {
                 Self::_try_query_summoner_v4_by_summoner_name(inner.clone(), region, name_str.clone())
             }).await
@@ -79,22 +276,23 @@ impl Context {
     async fn _try_query_summoner_v4_by_summoner_name(
         inner : Arc<ContextInner>, region : Region, summoner_name : String)->Result<summoner_v4::SummonerDto> {
 
-        let uri = Self::region_uri(region) + &summoner_v4::by_name_uri(&summoner_name);
-        let endpoint_ids = [Id::from_region(region), 
-                            Id::from_service(region, Service::SummonerV4), 
-                            Id::from_method(Service::SummonerV4, summoner_v4::Method::ByName as u32)];
+        let host = Service::SummonerV4.host(region);
+        let uri = Self::host_uri(&inner, host) + &summoner_v4::by_name_uri(&summoner_name);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::SummonerV4), 
+                            Id::from_method(host, Service::SummonerV4, summoner_v4::Method::ByName as u32)];
         let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
-        let data = response.json::<summoner_v4::SummonerDto>().await?;
+        let data = response.json::<summoner_v4::SummonerDto>()?;
         Ok(data)
     }
 
     #[allow(dead_code)]
     pub async fn query_summoner_v4_by_account(
-        &self, region : Region, encrypted_account_id : &str, retry_count : usize)->Result<summoner_v4::SummonerDto> {
+        &self, region : Region, encrypted_account_id : &str)->Result<summoner_v4::SummonerDto> {
 
         let inner = self.inner.clone();
         let account_id_str = encrypted_account_id.to_string();
-        Self::query_with_retry(retry_count,
+        Self::query_with_retry(&self.inner.retry_policy,
             move || {
                 Self::_try_query_summoner_v4_by_account(inner.clone(), region, account_id_str.clone())
             }).await
@@ -110,50 +308,152 @@ impl Context {
     async fn _try_query_summoner_v4_by_account(
         inner : Arc<ContextInner>, region : Region, encrypted_account_id : String)->Result<summoner_v4::SummonerDto> {
 
-        let uri = Self::region_uri(region) + &summoner_v4::by_account_uri(&encrypted_account_id);
-        let endpoint_ids = [Id::from_region(region), 
-                            Id::from_service(region, Service::SummonerV4), 
-                            Id::from_method(Service::SummonerV4, summoner_v4::Method::ByAccount as u32)];
+        let host = Service::SummonerV4.host(region);
+        let uri = Self::host_uri(&inner, host) + &summoner_v4::by_account_uri(&encrypted_account_id);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::SummonerV4), 
+                            Id::from_method(host, Service::SummonerV4, summoner_v4::Method::ByAccount as u32)];
+        let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
+        let data = response.json::<summoner_v4::SummonerDto>()?;
+        Ok(data)
+    }
+
+    pub async fn query_summoner_v4_by_puuid(
+        &self, region : Region, puuid : &str)->Result<summoner_v4::SummonerDto> {
+
+        let inner = self.inner.clone();
+        let puuid_str = puuid.to_string();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_summoner_v4_by_puuid(inner.clone(), region, puuid_str.clone())
+            }).await
+    }
+
+    pub async fn try_query_summoner_v4_by_puuid(
+        &self, region : Region, puuid : &str)->Result<summoner_v4::SummonerDto> {
+
+        Self::_try_query_summoner_v4_by_puuid(self.inner.clone(), region, puuid.to_string()).await
+    }
+
+    async fn _try_query_summoner_v4_by_puuid(
+        inner : Arc<ContextInner>, region : Region, puuid : String)->Result<summoner_v4::SummonerDto> {
+
+        let host = Service::SummonerV4.host(region);
+        let uri = Self::host_uri(&inner, host) + &summoner_v4::by_puuid_uri(&puuid);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::SummonerV4), 
+                            Id::from_method(host, Service::SummonerV4, summoner_v4::Method::ByPuuid as u32)];
+        let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
+        let data = response.json::<summoner_v4::SummonerDto>()?;
+        Ok(data)
+    }
+
+    pub async fn query_summoner_v4_by_summoner_id(
+        &self, region : Region, encrypted_summoner_id : &str)->Result<summoner_v4::SummonerDto> {
+
+        let inner = self.inner.clone();
+        let summoner_id_str = encrypted_summoner_id.to_string();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_summoner_v4_by_summoner_id(inner.clone(), region, summoner_id_str.clone())
+            }).await
+    }
+
+    pub async fn try_query_summoner_v4_by_summoner_id(
+        &self, region : Region, encrypted_summoner_id : &str)->Result<summoner_v4::SummonerDto> {
+
+        Self::_try_query_summoner_v4_by_summoner_id(self.inner.clone(), region, encrypted_summoner_id.to_string()).await
+    }
+
+    async fn _try_query_summoner_v4_by_summoner_id(
+        inner : Arc<ContextInner>, region : Region, encrypted_summoner_id : String)->Result<summoner_v4::SummonerDto> {
+
+        let host = Service::SummonerV4.host(region);
+        let uri = Self::host_uri(&inner, host) + &summoner_v4::by_summoner_id_uri(&encrypted_summoner_id);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::SummonerV4), 
+                            Id::from_method(host, Service::SummonerV4, summoner_v4::Method::BySummonerId as u32)];
         let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
-        let data = response.json::<summoner_v4::SummonerDto>().await?;
+        let data = response.json::<summoner_v4::SummonerDto>()?;
         Ok(data)
     }
     
     /* MATCH V4 METHODS */
     pub async fn query_match_v4_matchlist_by_account(
-        &self, region : Region, encrypted_account_id : &str, retry_count : usize) -> Result<match_v4::MatchlistDto> {
+        &self, region : Region, encrypted_account_id : &str) -> Result<match_v4::MatchlistDto> {
+
+        self.query_match_v4_matchlist_by_account_with_filter(region, encrypted_account_id, &match_v4::MatchlistFilter::default()).await
+    }
+
+    pub async fn try_query_match_v4_matchlist_by_account(
+        &self, region : Region, encrypted_account_id : &str) -> Result<match_v4::MatchlistDto> {
+
+        self.try_query_match_v4_matchlist_by_account_with_filter(region, encrypted_account_id, &match_v4::MatchlistFilter::default()).await
+    }
+
+    pub async fn query_match_v4_matchlist_by_account_with_filter(
+        &self, region : Region, encrypted_account_id : &str, filter : &match_v4::MatchlistFilter) -> Result<match_v4::MatchlistDto> {
 
         let inner = self.inner.clone();
         let account_id_str = encrypted_account_id.to_string();
-        Self::query_with_retry(retry_count,
+        let filter = filter.clone();
+        Self::query_with_retry(&self.inner.retry_policy,
             move || {
-                Self::_try_query_match_v4_matchlist_by_account(inner.clone(), region, account_id_str.clone())
+                Self::_try_query_match_v4_matchlist_by_account(inner.clone(), region, account_id_str.clone(), filter.clone())
             }).await
     }
 
-    pub async fn try_query_match_v4_matchlist_by_account(
-        &self, region : Region, encrypted_account_id : &str) -> Result<match_v4::MatchlistDto> {
+    pub async fn try_query_match_v4_matchlist_by_account_with_filter(
+        &self, region : Region, encrypted_account_id : &str, filter : &match_v4::MatchlistFilter) -> Result<match_v4::MatchlistDto> {
 
-        Self::_try_query_match_v4_matchlist_by_account(self.inner.clone(), region, encrypted_account_id.to_string()).await
+        Self::_try_query_match_v4_matchlist_by_account(self.inner.clone(), region, encrypted_account_id.to_string(), filter.clone()).await
     }
 
     async fn _try_query_match_v4_matchlist_by_account(
-        inner : Arc<ContextInner>, region : Region, encrypted_account_id : String) -> Result<match_v4::MatchlistDto> {
+        inner : Arc<ContextInner>, region : Region, encrypted_account_id : String, filter : match_v4::MatchlistFilter) -> Result<match_v4::MatchlistDto> {
         
-        let uri = Self::region_uri(region) + &match_v4::matchlist_by_account_uri(&encrypted_account_id);
-        let endpoint_ids = [Id::from_region(region), 
-                            Id::from_service(region, Service::MatchV4), 
-                            Id::from_method(Service::MatchV4, match_v4::Method::MatchlistByAccount as u32)];
+        let host = Service::MatchV4.host(region);
+        let uri = Self::host_uri(&inner, host) + &match_v4::matchlist_by_account_uri(&encrypted_account_id, &filter);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::MatchV4), 
+                            Id::from_method(host, Service::MatchV4, match_v4::Method::MatchlistByAccount as u32)];
         let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
-        let data = response.json::<match_v4::MatchlistDto>().await?;
+        let data = response.json::<match_v4::MatchlistDto>()?;
         Ok(data)
     }
 
+    /// Streams a player's whole match history, newest first. Pages
+    /// are fetched as the stream is polled, each through the same
+    /// rate limiting (and retries) as `query_match_v4_matchlist_by_account_with_filter`.
+    /// 
+    /// # Arguments
+    /// 
+    /// `region` - the region the player plays in
+    /// `encrypted_account_id` - the player's encrypted account id
+    /// `filter` - the matches to keep. Its `begin_index` is where the stream
+    ///     starts, and its `end_index` and `begin_time` bound where it ends
+    /// 
+    /// # Return
+    /// 
+    /// A stream of the matches, which ends once the history runs out
+    /// or a bound is hit. If a page can't be fetched the error is the
+    /// last item of the stream.
+    pub fn match_v4_matchlist_stream_by_account(
+        &self, region : Region, encrypted_account_id : &str, filter : &match_v4::MatchlistFilter)
+        -> impl Stream<Item = Result<match_v4::MatchReferenceDto>> {
+
+        let pager = MatchlistPager::new(self.inner.clone(), region, encrypted_account_id, filter);
+        stream::unfold(pager, |mut pager| async move {
+            let item = pager.next().await?;
+            Some((item, pager))
+        })
+    }
+
     pub async fn query_match_v4_match_by_id(
-        &self, region : Region, match_id : i64, retry_count : usize) -> Result<match_v4::MatchDto> {
+        &self, region : Region, match_id : i64) -> Result<match_v4::MatchDto> {
 
         let inner = self.inner.clone();
-        Self::query_with_retry(retry_count,
+        Self::query_with_retry(&self.inner.retry_policy,
             move || {
                 Self::_try_query_match_v4_match_by_id(inner.clone(), region, match_id)
             }).await
@@ -169,234 +469,895 @@ impl Context {
     async fn _try_query_match_v4_match_by_id(
         inner : Arc<ContextInner>, region : Region, match_id : i64) -> Result<match_v4::MatchDto> {
 
-        let uri = Self::region_uri(region) + &match_v4::match_by_id_uri(match_id);
-        let endpoint_ids = [Id::from_region(region), 
-                            Id::from_service(region, Service::MatchV4), 
-                            Id::from_method(Service::MatchV4, match_v4::Method::MatchById as u32)];
-        let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
-        let data = response.json::<match_v4::MatchDto>().await?;
-        Ok(data)
+        let host = Service::MatchV4.host(region);
+        let path = match_v4::match_by_id_uri(match_id);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::MatchV4), 
+                            Id::from_method(host, Service::MatchV4, match_v4::Method::MatchById as u32)];
+        Self::send_cached_query::<match_v4::MatchDto>(inner.clone(), host, &path, &endpoint_ids).await
     }
 
-    /// A helper which takes an async closure to save on typing for the
-    async fn query_with_retry<T, F>(retry_count : usize, query_func : impl Fn() -> F ) -> Result<T> 
-    where F : std::future::Future<Output=Result<T>> + Send {
+    pub async fn query_match_v4_timeline_by_match_id(
+        &self, region : Region, match_id : i64) -> Result<match_v4::MatchTimelineDto> {
 
-        let mut res = query_func().await;
+        let inner = self.inner.clone();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_match_v4_timeline_by_match_id(inner.clone(), region, match_id)
+            }).await
+    }
 
-        for _ in 0..retry_count {
-            match res {
-                Ok(_) => return res,
-                Err(e) if e.can_retry() => {
-                    let retry_time = e.retry_time().unwrap().clone(); // clone the time so the future is Send
-                    println!("retrying in: {:?}", &retry_time);
-                    tokio::time::delay_for(retry_time).await
-                },
-                _  => {}
-            }
-            res = query_func().await;
-        }
+    pub async fn try_query_match_v4_timeline_by_match_id(
+        &self, region : Region, match_id : i64) -> Result<match_v4::MatchTimelineDto> {
 
-        res.chain_err(|| "Retry count exceeded")
+        Self::_try_query_match_v4_timeline_by_match_id(self.inner.clone(), region, match_id).await
     }
 
-    /// The workhorse method for synhrnous querying. We check internal state
-    /// make sure the query is safe to execute (e.g. the endpoint isn't on cooldown and we can send),
-    /// sends the request, blocks, caches rate-limiting related information,
-    /// then returns the server response. If anything happens along the way or the server responds with
-    /// anything but 200 - OK we return the .
-    /// 
-    /// # Arguments
-    /// 
-    /// `uri` - the uri to execute the GET request against
-    /// `endpoint_ids` - identifiers of affected endpoints
-    /// 
-    /// # Remarks
-    /// 
-    /// This is the primary yeild point that could lead to panics in our `endpoints`
-    /// member, which is a `RefCell`. The only thing to watch out for is to make
-    /// sure that we are not holding the lock when we yield out or risk poisoning the mutex on the endpoints.
-    /// 
-    /// # Return
-    /// 
-    /// A result indicating the reqwest::Response 
-    /// if one was received from the server (otherwise an error)
-    async fn send_query(inner : Arc<ContextInner>, uri : &str, endpoint_ids : &[Id])->Result<Response> {
+    async fn _try_query_match_v4_timeline_by_match_id(
+        inner : Arc<ContextInner>, region : Region, match_id : i64) -> Result<match_v4::MatchTimelineDto> {
 
-        Self::prepare_to_query(inner.clone(), &endpoint_ids).await?;
-        let response = inner.client.get(uri)
-            .header("X-Riot-Token", &inner.api_key)
-            .send().await?;
-        Self::handle_response(inner.clone(), response, endpoint_ids).await
+        let host = Service::MatchV4.host(region);
+        let path = match_v4::timeline_by_match_id_uri(match_id);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::MatchV4), 
+                            Id::from_method(host, Service::MatchV4, match_v4::Method::TimelineByMatchId as u32)];
+        Self::send_cached_query::<match_v4::MatchTimelineDto>(inner.clone(), host, &path, &endpoint_ids).await
     }
 
-    /// Call this after the query is sent to handle any internal state
-    /// updates using the response.
-    /// 
-    /// > **NOTE**: this will consume the response proivded so call it last
-    /// 
-    /// # Arguments
-    /// 
-    /// `response` : the server response
-    /// `endpoint_ids` : the identifiers for the affected endpoints
-    /// 
-    /// # Return
-    /// 
-    /// A `Result`, which is the `Response` provided as an argument 
-    /// if there was no error, otherwise returns the error.
-    async fn handle_response(
-        inner : Arc<ContextInner>, response : Response, endpoint_ids : &[Id]) -> Result<Response> {
-        
-        // do any extra work or update internal state first
-        match response.status() {
-            StatusCode::OK => Self::cache_rate_limits(inner.clone(), &response, endpoint_ids).await?,
-            _ => { }
-        }
+    /* MATCH V5 METHODS */
+    pub async fn query_match_v5_match_ids_by_puuid(
+        &self, region : Region, puuid : &str) -> Result<Vec<String>> {
 
-        //now that internal state is updated, make a state transition for endpoints
-        Self::handle_status_transitions(inner.clone(), response.status(), endpoint_ids).await?;
-        match response.error_for_status() {
-            Ok(r) => Ok(r),
-            Err(e) => Err(Error::from(e)),
-        }
+        let inner = self.inner.clone();
+        let puuid_str = puuid.to_string();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_match_v5_match_ids_by_puuid(inner.clone(), region, puuid_str.clone())
+            }).await
     }
 
-    /// Helper method to avoid retyping the same thing over and over. Takes a state transition function
-    /// and applies it to each of the endpoitns specified by region, service, and method. The
-    /// transition function uses the result and the current status of any given endpoint to alter the endpoints
-    /// current status.
-    /// 
-    /// # Arguments
-    /// 
-    /// `status_code` : the status code the server responded with
-    /// `endpoint_ids` : the identifiers for the affected endpoints
-    async fn handle_status_transitions(
-        inner : Arc<ContextInner>, status_code : StatusCode, endpoint_ids : &[Id]) -> Result<()>{
+    pub async fn try_query_match_v5_match_ids_by_puuid(
+        &self, region : Region, puuid : &str) -> Result<Vec<String>> {
 
-        let endpoints_ref = &mut inner.endpoints.lock().await;
+        Self::_try_query_match_v5_match_ids_by_puuid(self.inner.clone(), region, puuid.to_string()).await
+    }
 
-        match status_code {
+    async fn _try_query_match_v5_match_ids_by_puuid(
+        inner : Arc<ContextInner>, region : Region, puuid : String) -> Result<Vec<String>> {
 
-            //update all
-            StatusCode::OK => {
+        let host = Service::MatchV5.host(region);
+        let uri = Self::host_uri(&inner, host) + &match_v5::match_ids_by_puuid_uri(&puuid);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::MatchV5), 
+                            Id::from_method(host, Service::MatchV5, match_v5::Method::MatchIdsByPuuid as u32)];
+        let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
+        let data = response.json::<Vec<String>>()?;
+        Ok(data)
+    }
 
-                for id in endpoint_ids {
-                    let ep = endpoints_ref.get_mut(id).unwrap();
-                    ep.update_status_200();
-                }
-            }
+    pub async fn query_match_v5_match_by_id(
+        &self, region : Region, match_id : &str) -> Result<match_v5::MatchDto> {
 
-            //update all then find likely offending point and set cd
-            StatusCode::TOO_MANY_REQUESTS => {
+        let inner = self.inner.clone();
+        let match_id_str = match_id.to_string();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_match_v5_match_by_id(inner.clone(), region, match_id_str.clone())
+            }).await
+    }
 
-                let mut already_cd = false;
-                for id in endpoint_ids {
-                    let ep = endpoints_ref.get_mut(id).unwrap();
-                    ep.update_status_400();
+    pub async fn try_query_match_v5_match_by_id(
+        &self, region : Region, match_id : &str) -> Result<match_v5::MatchDto> {
 
-                    if let endpoint::Status::Cooldown(_) = ep.status() {
-                        already_cd = true;
-                    }
-                }
+        Self::_try_query_match_v5_match_by_id(self.inner.clone(), region, match_id.to_string()).await
+    }
 
-                // if not on cooldown, force a stopgap cooldown to avoid more 400s
-                if !already_cd {
-                    
-                    // grab most likely to cd
-                    let mut likely_cd : Option<(u64, tokio::time::Duration)> = None; // (bucket, duration)
-                    let mut likely_cd_ep_id : Option<Id> = None;
-                    for id in endpoint_ids {
+    async fn _try_query_match_v5_match_by_id(
+        inner : Arc<ContextInner>, region : Region, match_id : String) -> Result<match_v5::MatchDto> {
 
-                        let ep = endpoints_ref.get_mut(id).unwrap();
+        let host = Service::MatchV5.host(region);
+        let path = match_v5::match_by_id_uri(&match_id);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::MatchV5), 
+                            Id::from_method(host, Service::MatchV5, match_v5::Method::MatchById as u32)];
+        Self::send_cached_query::<match_v5::MatchDto>(inner.clone(), host, &path, &endpoint_ids).await
+    }
 
-                        if let Some((until_cd, cd_dur)) = ep.most_likely_cd() {
-                            if likely_cd.is_none() || until_cd < likely_cd.unwrap().0 {
-                                likely_cd = Some((until_cd,cd_dur));
-                                likely_cd_ep_id = Some(*id);
-                            }
-                        }
-                    }
+    /* LEAGUE V4 METHODS */
+    pub async fn query_league_v4_challenger_league(
+        &self, region : Region, queue : league_v4::Queue) -> Result<league_v4::LeagueListDto> {
 
-                    // at least one in known state: set an approximate cooldown
-                    // (will likely be overwritten in a moment when the last
-                    // response in the cd window is received, and
-                    // it calls ep.update_status_200())
-                    if let Some(id) = likely_cd_ep_id {
+        let inner = self.inner.clone();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_league_v4_challenger_league(inner.clone(), region, queue)
+            }).await
+    }
 
-                        let ep = endpoints_ref.get_mut(&id).unwrap();
-                        println!("forcing cooldown {:?}!", likely_cd.unwrap().1);
-                        ep.force_cd(likely_cd.unwrap().1);
-                    }
-                    // they're all in unkown state? Then cd all of them
-                    else {
-                        for id in endpoint_ids {
-                            let ep = endpoints_ref.get_mut(&id).unwrap();
-                            let dur = tokio::time::Duration::from_secs(15);
-                            println!("forcing cooldown {:?}!", &dur);
-                            ep.force_cd(dur);
-                        }
-                    }
-                }
+    pub async fn try_query_league_v4_challenger_league(
+        &self, region : Region, queue : league_v4::Queue) -> Result<league_v4::LeagueListDto> {
 
-            },
+        Self::_try_query_league_v4_challenger_league(self.inner.clone(), region, queue).await
+    }
 
-            // else do nothing
-            _ => {},
-        }
+    async fn _try_query_league_v4_challenger_league(
+        inner : Arc<ContextInner>, region : Region, queue : league_v4::Queue) -> Result<league_v4::LeagueListDto> {
 
-        // now get most likely error
-        endpoint_ids.iter()
-                    .map(|id| endpoints_ref.get(&id).unwrap().error_for_status())
-                    .collect()
+        let host = Service::LeagueV4.host(region);
+        let uri = Self::host_uri(&inner, host) + &league_v4::challenger_league_uri(queue);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::LeagueV4), 
+                            Id::from_method(host, Service::LeagueV4, league_v4::Method::ChallengerLeague as u32)];
+        let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
+        let data = response.json::<league_v4::LeagueListDto>()?;
+        Ok(data)
     }
 
-    /// Uses the response to cache the 
-    /// most-recently seen rate limits from the server
-    /// This method mainly massages the 
-    /// inputs and does parsing so the
-    /// buckets can be updated properly
-    ///
-    /// # Arguments
-    /// 
-    /// * `response` - a reference to the response 
-    ///     given by the lol server (response code must be 200 - ok)
-    /// `endpoint_ids` : the identifiers for the affected endpoints
-    /// 
-    /// # Remarks
-    /// 
-    /// This is used only after receiving a 200 OK and should not be used elsewhere, for it
-    /// will panic. This is separately in its own function primarily for convenience/readability.
-    async fn cache_rate_limits(
-        inner : Arc<ContextInner>, response : &Response, endpoint_ids : &[Id]) -> Result<()> {
+    pub async fn query_league_v4_grandmaster_league(
+        &self, region : Region, queue : league_v4::Queue) -> Result<league_v4::LeagueListDto> {
 
-        let endpoints_ref = &mut inner.endpoints.lock().await;
+        let inner = self.inner.clone();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_league_v4_grandmaster_league(inner.clone(), region, queue)
+            }).await
+    }
 
-        let date_str = response.headers().get("Date").unwrap().to_str().unwrap();
-        let response_dt : DateTime<Utc> = DateTime::from(DateTime::parse_from_rfc2822(date_str).unwrap());
+    pub async fn try_query_league_v4_grandmaster_league(
+        &self, region : Region, queue : league_v4::Queue) -> Result<league_v4::LeagueListDto> {
 
-        // cache app limits if more recent
-        for id in endpoint_ids {
+        Self::_try_query_league_v4_grandmaster_league(self.inner.clone(), region, queue).await
+    }
 
-            // use the appropriate header for region endpoint rate limiting
-            if id.is_region() {
-                let region_ep  = endpoints_ref.get_mut(id).unwrap();
-                if (response_dt - region_ep.last_update_time()) > chrono::Duration::zero() {
+    async fn _try_query_league_v4_grandmaster_league(
+        inner : Arc<ContextInner>, region : Region, queue : league_v4::Queue) -> Result<league_v4::LeagueListDto> {
 
-                    let limits = Self::get_header_as_rate_limit(&response, "X-App-Rate-Limit")?;
-                    let counts = Self::get_header_as_rate_limit(&response, "X-App-Rate-Limit-Count")?;
+        let host = Service::LeagueV4.host(region);
+        let uri = Self::host_uri(&inner, host) + &league_v4::grandmaster_league_uri(queue);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::LeagueV4), 
+                            Id::from_method(host, Service::LeagueV4, league_v4::Method::GrandmasterLeague as u32)];
+        let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
+        let data = response.json::<league_v4::LeagueListDto>()?;
+        Ok(data)
+    }
 
-                    region_ep.update_buckets(&limits, &counts, DateTime::from(response_dt));
-                }
-            }
-            // use the appropriate header for method endpoint rate limiting
+    pub async fn query_league_v4_master_league(
+        &self, region : Region, queue : league_v4::Queue) -> Result<league_v4::LeagueListDto> {
+
+        let inner = self.inner.clone();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_league_v4_master_league(inner.clone(), region, queue)
+            }).await
+    }
+
+    pub async fn try_query_league_v4_master_league(
+        &self, region : Region, queue : league_v4::Queue) -> Result<league_v4::LeagueListDto> {
+
+        Self::_try_query_league_v4_master_league(self.inner.clone(), region, queue).await
+    }
+
+    async fn _try_query_league_v4_master_league(
+        inner : Arc<ContextInner>, region : Region, queue : league_v4::Queue) -> Result<league_v4::LeagueListDto> {
+
+        let host = Service::LeagueV4.host(region);
+        let uri = Self::host_uri(&inner, host) + &league_v4::master_league_uri(queue);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::LeagueV4), 
+                            Id::from_method(host, Service::LeagueV4, league_v4::Method::MasterLeague as u32)];
+        let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
+        let data = response.json::<league_v4::LeagueListDto>()?;
+        Ok(data)
+    }
+
+    pub async fn query_league_v4_entries(
+        &self, region : Region, queue : league_v4::Queue, tier : league_v4::Tier, division : league_v4::Division, page : i32) -> Result<Vec<league_v4::LeagueEntryDto>> {
+
+        let inner = self.inner.clone();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_league_v4_entries(inner.clone(), region, queue, tier, division, page)
+            }).await
+    }
+
+    pub async fn try_query_league_v4_entries(
+        &self, region : Region, queue : league_v4::Queue, tier : league_v4::Tier, division : league_v4::Division, page : i32) -> Result<Vec<league_v4::LeagueEntryDto>> {
+
+        Self::_try_query_league_v4_entries(self.inner.clone(), region, queue, tier, division, page).await
+    }
+
+    async fn _try_query_league_v4_entries(
+        inner : Arc<ContextInner>, region : Region, queue : league_v4::Queue, tier : league_v4::Tier, division : league_v4::Division, page : i32) -> Result<Vec<league_v4::LeagueEntryDto>> {
+
+        let host = Service::LeagueV4.host(region);
+        let uri = Self::host_uri(&inner, host) + &league_v4::entries_uri(queue, tier, division, page);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::LeagueV4), 
+                            Id::from_method(host, Service::LeagueV4, league_v4::Method::Entries as u32)];
+        let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
+        let data = response.json::<Vec<league_v4::LeagueEntryDto>>()?;
+        Ok(data)
+    }
+
+    pub async fn query_league_v4_entries_by_summoner(
+        &self, region : Region, summoner_id : &str) -> Result<Vec<league_v4::LeagueEntryDto>> {
+
+        let inner = self.inner.clone();
+        let summoner_id_str = summoner_id.to_string();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_league_v4_entries_by_summoner(inner.clone(), region, summoner_id_str.clone())
+            }).await
+    }
+
+    pub async fn try_query_league_v4_entries_by_summoner(
+        &self, region : Region, summoner_id : &str) -> Result<Vec<league_v4::LeagueEntryDto>> {
+
+        Self::_try_query_league_v4_entries_by_summoner(self.inner.clone(), region, summoner_id.to_string()).await
+    }
+
+    async fn _try_query_league_v4_entries_by_summoner(
+        inner : Arc<ContextInner>, region : Region, summoner_id : String) -> Result<Vec<league_v4::LeagueEntryDto>> {
+
+        let host = Service::LeagueV4.host(region);
+        let uri = Self::host_uri(&inner, host) + &league_v4::entries_by_summoner_uri(&summoner_id);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::LeagueV4), 
+                            Id::from_method(host, Service::LeagueV4, league_v4::Method::EntriesBySummoner as u32)];
+        let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
+        let data = response.json::<Vec<league_v4::LeagueEntryDto>>()?;
+        Ok(data)
+    }
+
+    /* SPECTATOR V4 METHODS */
+    pub async fn query_spectator_v4_active_game_by_summoner(
+        &self, region : Region, summoner_id : &str) -> Result<spectator_v4::CurrentGameInfoDto> {
+
+        let inner = self.inner.clone();
+        let summoner_id_str = summoner_id.to_string();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_spectator_v4_active_game_by_summoner(inner.clone(), region, summoner_id_str.clone())
+            }).await
+    }
+
+    pub async fn try_query_spectator_v4_active_game_by_summoner(
+        &self, region : Region, summoner_id : &str) -> Result<spectator_v4::CurrentGameInfoDto> {
+
+        Self::_try_query_spectator_v4_active_game_by_summoner(self.inner.clone(), region, summoner_id.to_string()).await
+    }
+
+    async fn _try_query_spectator_v4_active_game_by_summoner(
+        inner : Arc<ContextInner>, region : Region, summoner_id : String) -> Result<spectator_v4::CurrentGameInfoDto> {
+
+        let host = Service::SpectatorV4.host(region);
+        let uri = Self::host_uri(&inner, host) + &spectator_v4::active_game_by_summoner_uri(&summoner_id);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::SpectatorV4), 
+                            Id::from_method(host, Service::SpectatorV4, spectator_v4::Method::ActiveGameBySummoner as u32)];
+        let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
+        let data = response.json::<spectator_v4::CurrentGameInfoDto>()?;
+        Ok(data)
+    }
+
+    pub async fn query_spectator_v4_featured_games(
+        &self, region : Region) -> Result<spectator_v4::FeaturedGamesDto> {
+
+        let inner = self.inner.clone();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_spectator_v4_featured_games(inner.clone(), region)
+            }).await
+    }
+
+    pub async fn try_query_spectator_v4_featured_games(
+        &self, region : Region) -> Result<spectator_v4::FeaturedGamesDto> {
+
+        Self::_try_query_spectator_v4_featured_games(self.inner.clone(), region).await
+    }
+
+    async fn _try_query_spectator_v4_featured_games(
+        inner : Arc<ContextInner>, region : Region) -> Result<spectator_v4::FeaturedGamesDto> {
+
+        let host = Service::SpectatorV4.host(region);
+        let uri = Self::host_uri(&inner, host) + &spectator_v4::featured_games_uri();
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::SpectatorV4), 
+                            Id::from_method(host, Service::SpectatorV4, spectator_v4::Method::FeaturedGames as u32)];
+        let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
+        let data = response.json::<spectator_v4::FeaturedGamesDto>()?;
+        Ok(data)
+    }
+
+    /* CHAMPION MASTERY V4 METHODS */
+    pub async fn query_champion_mastery_v4_by_summoner(
+        &self, region : Region, summoner_id : &str) -> Result<Vec<champion_mastery_v4::ChampionMasteryDto>> {
+
+        let inner = self.inner.clone();
+        let summoner_id_str = summoner_id.to_string();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_champion_mastery_v4_by_summoner(inner.clone(), region, summoner_id_str.clone())
+            }).await
+    }
+
+    pub async fn try_query_champion_mastery_v4_by_summoner(
+        &self, region : Region, summoner_id : &str) -> Result<Vec<champion_mastery_v4::ChampionMasteryDto>> {
+
+        Self::_try_query_champion_mastery_v4_by_summoner(self.inner.clone(), region, summoner_id.to_string()).await
+    }
+
+    async fn _try_query_champion_mastery_v4_by_summoner(
+        inner : Arc<ContextInner>, region : Region, summoner_id : String) -> Result<Vec<champion_mastery_v4::ChampionMasteryDto>> {
+
+        let host = Service::ChampionMasteryV4.host(region);
+        let uri = Self::host_uri(&inner, host) + &champion_mastery_v4::by_summoner_uri(&summoner_id);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::ChampionMasteryV4), 
+                            Id::from_method(host, Service::ChampionMasteryV4, champion_mastery_v4::Method::BySummoner as u32)];
+        let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
+        let data = response.json::<Vec<champion_mastery_v4::ChampionMasteryDto>>()?;
+        Ok(data)
+    }
+
+    pub async fn query_champion_mastery_v4_by_summoner_and_champion(
+        &self, region : Region, summoner_id : &str, champion_id : i64) -> Result<champion_mastery_v4::ChampionMasteryDto> {
+
+        let inner = self.inner.clone();
+        let summoner_id_str = summoner_id.to_string();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_champion_mastery_v4_by_summoner_and_champion(inner.clone(), region, summoner_id_str.clone(), champion_id)
+            }).await
+    }
+
+    pub async fn try_query_champion_mastery_v4_by_summoner_and_champion(
+        &self, region : Region, summoner_id : &str, champion_id : i64) -> Result<champion_mastery_v4::ChampionMasteryDto> {
+
+        Self::_try_query_champion_mastery_v4_by_summoner_and_champion(self.inner.clone(), region, summoner_id.to_string(), champion_id).await
+    }
+
+    async fn _try_query_champion_mastery_v4_by_summoner_and_champion(
+        inner : Arc<ContextInner>, region : Region, summoner_id : String, champion_id : i64) -> Result<champion_mastery_v4::ChampionMasteryDto> {
+
+        let host = Service::ChampionMasteryV4.host(region);
+        let uri = Self::host_uri(&inner, host) + &champion_mastery_v4::by_summoner_and_champion_uri(&summoner_id, champion_id);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::ChampionMasteryV4), 
+                            Id::from_method(host, Service::ChampionMasteryV4, champion_mastery_v4::Method::BySummonerAndChampion as u32)];
+        let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
+        let data = response.json::<champion_mastery_v4::ChampionMasteryDto>()?;
+        Ok(data)
+    }
+
+    pub async fn query_champion_mastery_v4_score_by_summoner(
+        &self, region : Region, summoner_id : &str) -> Result<i32> {
+
+        let inner = self.inner.clone();
+        let summoner_id_str = summoner_id.to_string();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_champion_mastery_v4_score_by_summoner(inner.clone(), region, summoner_id_str.clone())
+            }).await
+    }
+
+    pub async fn try_query_champion_mastery_v4_score_by_summoner(
+        &self, region : Region, summoner_id : &str) -> Result<i32> {
+
+        Self::_try_query_champion_mastery_v4_score_by_summoner(self.inner.clone(), region, summoner_id.to_string()).await
+    }
+
+    async fn _try_query_champion_mastery_v4_score_by_summoner(
+        inner : Arc<ContextInner>, region : Region, summoner_id : String) -> Result<i32> {
+
+        let host = Service::ChampionMasteryV4.host(region);
+        let uri = Self::host_uri(&inner, host) + &champion_mastery_v4::score_by_summoner_uri(&summoner_id);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::ChampionMasteryV4), 
+                            Id::from_method(host, Service::ChampionMasteryV4, champion_mastery_v4::Method::ScoreBySummoner as u32)];
+        let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
+        let data = response.json::<i32>()?;
+        Ok(data)
+    }
+
+    /* ACCOUNT V1 METHODS */
+    pub async fn query_account_v1_by_riot_id(
+        &self, region : Region, game_name : &str, tag_line : &str) -> Result<account_v1::AccountDto> {
+
+        let inner = self.inner.clone();
+        let game_name_str = game_name.to_string();
+        let tag_line_str = tag_line.to_string();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_account_v1_by_riot_id(inner.clone(), region, game_name_str.clone(), tag_line_str.clone())
+            }).await
+    }
+
+    pub async fn try_query_account_v1_by_riot_id(
+        &self, region : Region, game_name : &str, tag_line : &str) -> Result<account_v1::AccountDto> {
+
+        Self::_try_query_account_v1_by_riot_id(self.inner.clone(), region, game_name.to_string(), tag_line.to_string()).await
+    }
+
+    async fn _try_query_account_v1_by_riot_id(
+        inner : Arc<ContextInner>, region : Region, game_name : String, tag_line : String) -> Result<account_v1::AccountDto> {
+
+        let host = Service::AccountV1.host(region);
+        let uri = Self::host_uri(&inner, host) + &account_v1::by_riot_id_uri(&game_name, &tag_line);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::AccountV1), 
+                            Id::from_method(host, Service::AccountV1, account_v1::Method::ByRiotId as u32)];
+        let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
+        let data = response.json::<account_v1::AccountDto>()?;
+        Ok(data)
+    }
+
+    pub async fn query_account_v1_by_puuid(
+        &self, region : Region, puuid : &str) -> Result<account_v1::AccountDto> {
+
+        let inner = self.inner.clone();
+        let puuid_str = puuid.to_string();
+        Self::query_with_retry(&self.inner.retry_policy,
+            move || {
+                Self::_try_query_account_v1_by_puuid(inner.clone(), region, puuid_str.clone())
+            }).await
+    }
+
+    pub async fn try_query_account_v1_by_puuid(
+        &self, region : Region, puuid : &str) -> Result<account_v1::AccountDto> {
+
+        Self::_try_query_account_v1_by_puuid(self.inner.clone(), region, puuid.to_string()).await
+    }
+
+    async fn _try_query_account_v1_by_puuid(
+        inner : Arc<ContextInner>, region : Region, puuid : String) -> Result<account_v1::AccountDto> {
+
+        let host = Service::AccountV1.host(region);
+        let uri = Self::host_uri(&inner, host) + &account_v1::by_puuid_uri(&puuid);
+        let endpoint_ids = [Id::from_host(host), 
+                            Id::from_service(host, Service::AccountV1), 
+                            Id::from_method(host, Service::AccountV1, account_v1::Method::ByPuuid as u32)];
+        let response = Self::send_query(inner.clone(), &uri, &endpoint_ids).await?;
+        let data = response.json::<account_v1::AccountDto>()?;
+        Ok(data)
+    }
+
+    /// A helper which takes an async closure to save on typing for the
+    /// retries. Failed queries are sent again for as long as the policy
+    /// allows, waiting in between as long as it says.
+    async fn query_with_retry<T, F>(policy : &RetryPolicy, query_func : impl Fn() -> F ) -> Result<T> 
+    where F : std::future::Future<Output=Result<T>> + Send {
+
+        let start = tokio::time::Instant::now();
+        let mut retries = 0;
+
+        loop {
+            let e = match query_func().await {
+                Ok(data) => return Ok(data),
+                Err(e) => e,
+            };
+            match policy.retry_delay(&e, retries, start.elapsed()) {
+                Some(retry_time) => {
+                    println!("retrying in: {:?}", &retry_time);
+                    tokio::time::delay_for(retry_time).await;
+                    retries += 1;
+                },
+                None if retries > 0 => return Err(e).chain_err(|| "Retry count exceeded"),
+                None => return Err(e),
+            }
+        }
+    }
+
+    /// The workhorse method for synhrnous querying. We check internal state
+    /// make sure the query is safe to execute (e.g. the endpoint isn't on cooldown and we can send),
+    /// waiting for room in the rate limits if need be,
+    /// sends the request, blocks, caches rate-limiting related information,
+    /// then returns the server response. If anything happens along the way or the server responds with
+    /// anything but 200 - OK we return the .
+    /// 
+    /// # Arguments
+    /// 
+    /// `uri` - the uri to execute the GET request against
+    /// `endpoint_ids` - identifiers of affected endpoints
+    /// 
+    /// # Remarks
+    /// 
+    /// This is the primary yeild point that could lead to panics in our `endpoints`
+    /// member, which is a `RefCell`. The only thing to watch out for is to make
+    /// sure that we are not holding the lock when we yield out or risk poisoning the mutex on the endpoints.
+    /// 
+    /// # Return
+    /// 
+    /// A result indicating the `RawResponse`
+    /// if one was received from the server (otherwise an error)
+    async fn send_query(inner : Arc<ContextInner>, uri : &str, endpoint_ids : &[Id])->Result<RawResponse> {
+
+        // recent responses are served from memory, without touching the rate limits
+        let method_id = endpoint_ids.iter().copied().find(Id::is_method);
+        let memory_cache = match (&inner.memory_cache, method_id) {
+            (Some(cache), Some(method_id)) if cache.caches(method_id) => Some((cache, method_id)),
+            _ => None,
+        };
+        if let Some((cache, method_id)) = memory_cache {
+            if let Some(response) = cache.get(method_id, uri) {
+                return Ok(response);
+            }
+        }
+
+        let response = Self::send_coalesced_query(inner.clone(), uri, endpoint_ids).await?;
+
+        if let Some((cache, method_id)) = memory_cache {
+            cache.put(method_id, uri, &response);
+        }
+        Ok(response)
+    }
+
+    /// Sends a query, unless an identical one (i.e. to the same uri) is
+    /// already in flight, in which case we wait for its response instead
+    /// of paying for the same data twice. Each caller deserializes its
+    /// own copy of the shared response.
+    /// 
+    /// # Arguments
+    /// 
+    /// `uri` - the uri to execute the GET request against
+    /// `endpoint_ids` - identifiers of affected endpoints
+    /// 
+    /// # Remarks
+    /// 
+    /// Unsuccessful responses (e.g. a 404) are shared as well, each caller
+    /// getting its own copy of the error. If the query failed any other way
+    /// (e.g. it was rate limited) the callers waiting on it send their own,
+    /// since the rate limits may have something different to say to each.
+    async fn send_coalesced_query(inner : Arc<ContextInner>, uri : &str, endpoint_ids : &[Id])->Result<RawResponse> {
+
+        loop {
+            // join the query in flight, or put ours in flight for others to join
+            let (sender, flight) = {
+                let mut in_flight = inner.in_flight.lock().unwrap();
+                match in_flight.get(uri) {
+                    Some((_, flight)) => (None, flight.clone()),
+                    None => {
+                        let (sender, receiver) = oneshot::channel::<FlightOutcome>();
+                        let flight = receiver.map(|outcome| outcome.ok()).boxed().shared();
+                        let flight_id = inner.next_flight_id.fetch_add(1, Ordering::Relaxed);
+                        in_flight.insert(uri.to_string(), (flight_id, flight.clone()));
+                        (Some((sender, flight_id)), flight)
+                    },
+                }
+            };
+
+            if let Some((sender, flight_id)) = sender {
+                // the guard takes the query out of flight even if we're dropped halfway
+                let guard = InFlightGuard { inner : inner.clone(), uri, flight_id };
+                let result = Self::send_to_server(inner.clone(), uri, endpoint_ids).await;
+                drop(guard);
+                let _ = sender.send(FlightOutcome::of(&result));
+                return result;
+            }
+
+            match flight.await {
+                Some(FlightOutcome::Response(response)) => return Ok(response),
+                Some(FlightOutcome::Status(status, body)) => return Err(Error::from_status(status, uri, body.as_bytes())),
+                Some(FlightOutcome::Failed) => return Self::send_to_server(inner.clone(), uri, endpoint_ids).await,
+                None => {}, // the query was dropped before it finished, so try again
+            }
+        }
+    }
+
+    /// Sends a query to the server (through the transport) with whichever
+    /// key has room in its rate limits, waiting for one first if need be,
+    /// and updates that key's rate limits with the response.
+    /// 
+    /// # Arguments
+    /// 
+    /// `uri` - the uri to execute the GET request against
+    /// `endpoint_ids` - identifiers of affected endpoints
+    async fn send_to_server(inner : Arc<ContextInner>, uri : &str, endpoint_ids : &[Id])->Result<RawResponse> {
+
+        let key = Self::prepare_to_query(inner.clone(), &endpoint_ids).await?;
+        match inner.transport.get(uri, &inner.keys[key].key).await {
+            Ok(response) => Self::handle_response(inner.clone(), key, uri, response, endpoint_ids).await,
+            Err(e) => {
+                Self::release_endpoints(inner.clone(), key, endpoint_ids).await;
+                Err(e)
+            },
+        }
+    }
+
+    /// Like `send_query`, but for queries whose data never changes, so
+    /// their responses can be kept in the context's disk cache (if it has one).
+    /// A cached response is served without touching the endpoints at all,
+    /// and so doesn't count against the rate limits.
+    /// 
+    /// # Arguments
+    /// 
+    /// `host` - the host to send the query to
+    /// `path` - the path of the query's uri, which is appended to the host's uri
+    /// `endpoint_ids` - identifiers of affected endpoints
+    /// 
+    /// # Remarks
+    /// 
+    /// Only bodies which deserialize are cached, and a cached body which
+    /// doesn't (e.g. one from an older version of the dtos) is evicted and
+    /// queried again, so a bad body never fails the same query for good.
+    /// 
+    /// # Return
+    /// 
+    /// A result indicating the deserialized data, either from the cache or the server
+    async fn send_cached_query<T : DeserializeOwned>(inner : Arc<ContextInner>, host : Host, path : &str, endpoint_ids : &[Id])->Result<T> {
+
+        let uri = Self::host_uri(&inner, host) + path;
+        let cache = match &inner.disk_cache {
+            Some(cache) => cache.clone(),
+            None => return Self::send_query(inner.clone(), &uri, endpoint_ids).await?.json::<T>(),
+        };
+
+        if let Some(body) = cache.get(host, path).await {
+            match serde_json::from_slice::<T>(&body) {
+                Ok(data) => return Ok(data),
+                Err(e) => {
+                    eprintln!("evicting cached {}: {}", uri, e);
+                    if let Err(e) = cache.remove(host, path).await {
+                        eprintln!("unable to evict cached {}: {}", uri, e);
+                    }
+                },
+            }
+        }
+        let response = Self::send_query(inner.clone(), &uri, endpoint_ids).await?;
+        let data = response.json::<T>()?;
+
+        // the response is still good even if we can't cache it
+        if let Err(e) = cache.put(host, path, response.body()).await {
+            eprintln!("unable to cache {}: {}", uri, e);
+        }
+        Ok(data)
+    }
+
+    /// Call this after the query is sent to handle any internal state
+    /// updates using the response.
+    /// 
+    /// > **NOTE**: this will consume the response proivded so call it last
+    /// 
+    /// # Arguments
+    /// 
+    /// `key` : the index of the api key the query was sent with
+    /// `uri` : the uri the query was sent to
+    /// `response` : the server response
+    /// `endpoint_ids` : the identifiers for the affected endpoints
+    /// 
+    /// # Return
+    /// 
+    /// A `Result`, which is the `Response` provided as an argument 
+    /// if there was no error, otherwise returns the error.
+    async fn handle_response(
+        inner : Arc<ContextInner>, key : usize, uri : &str, response : RawResponse, endpoint_ids : &[Id]) -> Result<RawResponse> {
+        
+        // do any extra work or update internal state first
+        // (the counts are cached before the query stops being in flight,
+        // so the query is never missing from both)
+        let cached = match response.status() {
+            StatusCode::OK => Self::cache_rate_limits(inner.clone(), key, &response, endpoint_ids).await,
+            _ => Ok(()),
+        };
+        Self::release_endpoints(inner.clone(), key, endpoint_ids).await;
+        cached?;
+
+        //now that internal state is updated, make a state transition for endpoints
+        Self::handle_status_transitions(inner.clone(), key, &response, endpoint_ids).await?;
+        if response.status().is_client_error() || response.status().is_server_error() {
+            return Err(Error::from_status(response.status(), uri, response.body()));
+        }
+        Ok(response)
+    }
+
+    /// Helper method to avoid retyping the same thing over and over. Takes a state transition function
+    /// and applies it to each of the endpoitns specified by region, service, and method. The
+    /// transition function uses the result and the current status of any given endpoint to alter the endpoints
+    /// current status.
+    /// 
+    /// # Arguments
+    /// 
+    /// `key` : the index of the api key the query was sent with
+    /// `response` : the server response
+    /// `endpoint_ids` : the identifiers for the affected endpoints
+    /// 
+    /// # Remarks
+    /// 
+    /// A 429 only puts the layer named by its `X-Rate-Limit-Type` header on cooldown
+    /// (e.g. a service 429 leaves the application limits alone), for as long as its
+    /// `Retry-After` header says. We only guess the cooldown if the header's missing.
+    async fn handle_status_transitions(
+        inner : Arc<ContextInner>, key : usize, response : &RawResponse, endpoint_ids : &[Id]) -> Result<()>{
+
+        let endpoints_ref = &mut inner.keys[key].endpoints.lock().await;
+        let status_code = response.status();
+
+        match status_code {
+
+            //update all
+            StatusCode::OK => {
+
+                for id in endpoint_ids {
+                    let ep = endpoints_ref.get_mut(id).unwrap();
+                    ep.update_status_200();
+                }
+            }
+
+            //find the offending endpoints and set cd
+            StatusCode::TOO_MANY_REQUESTS => {
+
+                let limit_type = Self::get_header_as_str(response, "X-Rate-Limit-Type").ok()
+                                    .and_then(|limit_type| limit_type.parse::<RateLimitType>().ok());
+                let retry_after = Self::get_header_as_str(response, "Retry-After").ok()
+                                    .and_then(|secs| secs.trim().parse::<u64>().ok())
+                                    .map(tokio::time::Duration::from_secs);
+
+                let offending_ids : Vec<Id> = endpoint_ids.iter().copied()
+                                    .filter(|id| limit_type.is_none_or(|limit_type| limit_type.applies_to(*id)))
+                                    .collect();
+
+                let cooldown = match retry_after {
+                    Some(retry_after) => {
+                        for id in offending_ids.iter() {
+                            endpoints_ref.get_mut(id).unwrap().force_cd(retry_after);
+                        }
+                        retry_after
+                    },
+                    None => Self::guess_cooldown(endpoints_ref, &offending_ids),
+                };
+
+                return Err(Error::from(ErrorKind::RateLimited(limit_type, cooldown)));
+            },
+
+            // else do nothing
+            _ => {},
+        }
+
+        // a successful response is good data, even if another query put us on cooldown meanwhile
+        if status_code.is_success() {
+            return Ok(());
+        }
+
+        // now get most likely error
+        endpoint_ids.iter()
+                    .map(|id| endpoints_ref.get(&id).unwrap().error_for_status())
+                    .collect()
+    }
+
+    /// Puts endpoints on a stopgap cooldown after a 429 which didn't say
+    /// how long to wait, guessing from the cached buckets which one
+    /// overflowed (or cooling them all down if we know nothing).
+    /// 
+    /// # Arguments
+    /// 
+    /// `endpoints_ref` : the locked endpoints
+    /// `endpoint_ids` : the identifiers for the possibly offending endpoints
+    /// 
+    /// # Return
+    /// 
+    /// The longest cooldown any of the endpoints is now on
+    fn guess_cooldown(endpoints_ref : &mut HashMap<Id, Endpoint>, endpoint_ids : &[Id]) -> tokio::time::Duration {
+
+        let mut already_cd = false;
+        for id in endpoint_ids {
+            let ep = endpoints_ref.get_mut(id).unwrap();
+            ep.update_status_400();
+
+            if let endpoint::Status::Cooldown(_) = ep.status() {
+                already_cd = true;
+            }
+        }
+
+        // if not on cooldown, force a stopgap cooldown to avoid more 400s
+        if !already_cd {
+            
+            // grab most likely to cd
+            let mut likely_cd : Option<(u64, tokio::time::Duration)> = None; // (bucket, duration)
+            let mut likely_cd_ep_id : Option<Id> = None;
+            for id in endpoint_ids {
+
+                let ep = endpoints_ref.get_mut(id).unwrap();
+
+                if let Some((until_cd, cd_dur)) = ep.most_likely_cd() {
+                    if likely_cd.is_none() || until_cd < likely_cd.unwrap().0 {
+                        likely_cd = Some((until_cd,cd_dur));
+                        likely_cd_ep_id = Some(*id);
+                    }
+                }
+            }
+
+            // at least one in known state: set an approximate cooldown
+            if let Some(id) = likely_cd_ep_id {
+
+                let ep = endpoints_ref.get_mut(&id).unwrap();
+                println!("forcing cooldown {:?}!", likely_cd.unwrap().1);
+                ep.force_cd(likely_cd.unwrap().1);
+            }
+            // they're all in unkown state? Then cd all of them
+            else {
+                for id in endpoint_ids {
+                    let ep = endpoints_ref.get_mut(&id).unwrap();
+                    let dur = tokio::time::Duration::from_secs(15);
+                    println!("forcing cooldown {:?}!", &dur);
+                    ep.force_cd(dur);
+                }
+            }
+        }
+
+        endpoint_ids.iter()
+            .filter_map(|id| match endpoints_ref.get(id).unwrap().status() {
+                endpoint::Status::Cooldown(cd_state) => cd_state.time_left(),
+                _ => None,
+            })
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Uses the response to cache the 
+    /// most-recently seen rate limits from the server
+    /// This method mainly massages the 
+    /// inputs and does parsing so the
+    /// buckets can be updated properly
+    ///
+    /// # Arguments
+    /// 
+    /// * `key` - the index of the api key the query was sent with
+    /// * `response` - a reference to the response 
+    ///     given by the lol server (response code must be 200 - ok)
+    /// `endpoint_ids` : the identifiers for the affected endpoints
+    /// 
+    /// # Remarks
+    /// 
+    /// This is used only after receiving a 200 OK and should not be used elsewhere, for it
+    /// will panic. This is separately in its own function primarily for convenience/readability.
+    async fn cache_rate_limits(
+        inner : Arc<ContextInner>, key : usize, response : &RawResponse, endpoint_ids : &[Id]) -> Result<()> {
+
+        let mut endpoints_ref = inner.keys[key].endpoints.lock().await;
+
+        let date_str = response.headers().get("Date").unwrap().to_str().unwrap();
+        let response_dt : DateTime<Utc> = DateTime::from(DateTime::parse_from_rfc2822(date_str).unwrap());
+
+        // cache app limits if more recent
+        let mut updates = Vec::new();
+        for id in endpoint_ids {
+
+            // use the appropriate header for host endpoint rate limiting
+            if id.is_host() {
+                let region_ep  = endpoints_ref.get_mut(id).unwrap();
+                if (response_dt - region_ep.last_update_time()) >= chrono::Duration::zero() {
+
+                    let limits = Self::get_header_as_rate_limit(&response, "X-App-Rate-Limit")?;
+                    let counts = Self::get_header_as_rate_limit(&response, "X-App-Rate-Limit-Count")?;
+
+                    region_ep.update_buckets(&limits, &counts, DateTime::from(response_dt));
+                    updates.push((*id, limits, counts));
+                }
+            }
+            // use the appropriate header for method endpoint rate limiting
             else if id.is_method() {
                 let method_ep  = endpoints_ref.get_mut(id).unwrap();
-                if (response_dt - method_ep.last_update_time()) > chrono::Duration::zero() {
+                if (response_dt - method_ep.last_update_time()) >= chrono::Duration::zero() {
 
                     let limits = Self::get_header_as_rate_limit(&response, "X-Method-Rate-Limit")?;
                     let counts = Self::get_header_as_rate_limit(&response, "X-Method-Rate-Limit-Count")?;
 
                     method_ep.update_buckets(&limits, &counts, DateTime::from(response_dt));
+                    updates.push((*id, limits, counts));
+                }
+            }
+        }
+
+        drop(endpoints_ref);
+
+        // pass the server's counts on, so the other processes learn about each other's
+        // queries. The query already went through, so a coordinator which can't be reached
+        // (e.g. while it restarts) only misses these counts rather than failing the query.
+        if let Some(coordinator) = &inner.coordinator {
+            for (id, limits, counts) in updates {
+                if let Err(e) = coordinator.update(inner.keys[key].fingerprint, id, limits, counts).await {
+                    eprintln!("unable to update the rate-limit coordinator: {}", e);
                 }
             }
         }
@@ -416,7 +1377,7 @@ impl Context {
     /// 
     /// The header value as a new String object or an error
     /// if the conversion failed.
-    fn get_header_as_str(response : &Response, header_name : &str) -> Result<String> {
+    fn get_header_as_str(response : &RawResponse, header_name : &str) -> Result<String> {
 
         let header_val = response.headers().get(header_name)
                          .chain_err(|| format!("Header {} not found.", header_name))?;
@@ -437,7 +1398,7 @@ impl Context {
     /// 
     /// The header value as a Vec(limit,bucket_size) on success
     /// or an error if the parse failed.
-    fn get_header_as_rate_limit(response : &Response, header_name : &str) -> Result<Vec<(u64,u64)>> {
+    fn get_header_as_rate_limit(response : &RawResponse, header_name : &str) -> Result<Vec<(u64,u64)>> {
         
         let limit_str = Self::get_header_as_str(&response, header_name)?;
 
@@ -457,54 +1418,188 @@ impl Context {
     }
 
     /// Updates some internal state prior to making the query to ensure that the endpoint we are about to
-    /// query is g2g (e.g. not on cooldown or the lol servers exploded or something)
+    /// query is g2g (e.g. not on cooldown or the lol servers exploded or something).
+    /// Waits until every endpoint of some key has room for the query, then counts the query
+    /// against all of them at once so concurrent queries can't overfill a bucket.
     /// 
     /// # Arguments
     /// 
     /// `endpoint_ids` : the identifiers for the affected endpoints
     /// 
+    /// # Remarks
+    /// 
+    /// The keys take turns being checked first, so the queries spread
+    /// evenly across the pool while every key has room. With a coordinator,
+    /// a key only has room once the coordinator has counted the query as well.
+    /// 
     /// # Return
     /// 
-    /// Gives a `Result` containin `()` on success, and
-    /// an error on failure.
+    /// Gives a `Result` containing the index of the key to send the query with
+    /// on success, and an error on failure (only if the context doesn't wait for capacity,
+    /// in which case it's an `EndpointNotReady` with the time until a key has room).
     async fn prepare_to_query(
-        inner : Arc<ContextInner>, endpoint_ids : &[Id]) -> Result<()>{
+        inner : Arc<ContextInner>, endpoint_ids : &[Id]) -> Result<usize>{
+
+        loop {
+            let first_key = inner.next_key.fetch_add(1, Ordering::Relaxed);
+            let mut shortest_wait : Option<tokio::time::Duration> = None;
+
+            for key in (0..inner.keys.len()).map(|offset| (first_key + offset) % inner.keys.len()) {
+
+                // update + check each endpoint, and reserve the query right
+                // away unless the coordinator has to count it first
+                let (mut wait, limits) = {
+                    let endpoints_ref = &mut inner.keys[key].endpoints.lock().await;
+                    let wait = Self::time_until_endpoints_ready(&inner, endpoints_ref, endpoint_ids);
+                    match (wait, &inner.coordinator) {
+                        (None, None) => {
+                            Self::reserve_endpoints(endpoints_ref, endpoint_ids);
+                            return Ok(key);
+                        },
+                        (None, Some(_)) => (None, endpoint_ids.iter().map(|id| (*id, endpoints_ref[id].limits())).collect()),
+                        (Some(wait), _) => (Some(wait), Vec::new()),
+                    }
+                };
+
+                // the other processes sharing the key have to have room too. The key's
+                // endpoints stay unlocked while the coordinator answers, so the key's
+                // other queries aren't held up by the round trip
+                if let (None, Some(coordinator)) = (wait, &inner.coordinator) {
+                    wait = coordinator.reserve(inner.keys[key].fingerprint, limits).await?;
+                    if wait.is_none() {
+                        // another query may have taken the room in the meantime, in
+                        // which case the coordinator has to give the slot back
+                        {
+                            let endpoints_ref = &mut inner.keys[key].endpoints.lock().await;
+                            wait = Self::time_until_endpoints_ready(&inner, endpoints_ref, endpoint_ids);
+                            if wait.is_none() {
+                                Self::reserve_endpoints(endpoints_ref, endpoint_ids);
+                                return Ok(key);
+                            }
+                        }
+                        if let Err(e) = coordinator.release(inner.keys[key].fingerprint, endpoint_ids.to_vec()).await {
+                            eprintln!("unable to release a slot with the rate-limit coordinator: {}", e);
+                        }
+                    }
+                }
+
+                if let Some(wait) = wait {
+                    shortest_wait = Some(shortest_wait.map_or(wait, |shortest| shortest.min(wait)));
+                }
+            }
+            let wait = shortest_wait.unwrap();
 
-        // update + check region
-        let endpoints_ref = &mut inner.endpoints.lock().await;
+            if !inner.wait_for_capacity {
+                let status = endpoint::Status::Cooldown(endpoint::CooldownState::new(wait));
+                return Err(Error::from(ErrorKind::EndpointNotReady(status)));
+            }
+            tokio::time::delay_for(wait).await;
+        }
+    }
+
+    /// Updates and checks the endpoints of a query on a single key
+    /// 
+    /// # Return
+    /// 
+    /// `None` if every endpoint has room for the query, otherwise
+    /// how long until they all do
+    fn time_until_endpoints_ready(
+        inner : &ContextInner, endpoints : &mut HashMap<Id, Endpoint>, endpoint_ids : &[Id]) -> Option<tokio::time::Duration> {
 
+        let mut wait : Option<tokio::time::Duration> = None;
         for id in endpoint_ids {
-            let ep  = endpoints_ref.entry(*id).or_insert(Endpoint::new());
+            let ep = endpoints.entry(*id).or_insert_with(|| Self::new_endpoint(inner, *id));
             ep.update_status_pre_query();
-            ep.error_for_status()?;
+            wait = wait.max(ep.time_until_ready());
         }
+        wait
+    }
 
-        Ok(())
+    /// Counts a query against every one of its endpoints on a single key
+    fn reserve_endpoints(endpoints : &mut HashMap<Id, Endpoint>, endpoint_ids : &[Id]) {
+        for id in endpoint_ids {
+            endpoints.get_mut(id).unwrap().reserve();
+        }
+    }
+
+    /// Call this once a query reserved by `prepare_to_query` is
+    /// responded to (or failed to send) so it stops counting as in flight
+    /// 
+    /// # Arguments
+    /// 
+    /// `key` : the index of the api key the query was reserved on
+    /// `endpoint_ids` : the identifiers for the affected endpoints
+    async fn release_endpoints(inner : Arc<ContextInner>, key : usize, endpoint_ids : &[Id]) {
+
+        let endpoints_ref = &mut inner.keys[key].endpoints.lock().await;
+        for id in endpoint_ids {
+            if let Some(ep) = endpoints_ref.get_mut(id) {
+                ep.release();
+            }
+        }
+    }
+
+    /// Creates the endpoint for an id the first time it's queried.
+    /// Host endpoints start out with the configured application
+    /// limits, everything else learns its limits from the responses.
+    fn new_endpoint(inner : &ContextInner, id : Id) -> Endpoint {
+        if id.is_host() && !inner.app_rate_limits.is_empty() {
+            Endpoint::with_limits(&inner.app_rate_limits)
+        }
+        else {
+            Endpoint::new()
+        }
     }
 
-    /// Takes the region enum and provides the formatted uri
-    /// that prefixes calls to services in this region
+    /// Takes the host and provides the formatted uri
+    /// that prefixes calls to services on this host
     /// 
     /// #Arguments
     /// 
-    /// `region` - the region to construct a query prefix string for
+    /// `inner` - the context data holding the base uri template
+    /// `host` - the host to construct a query prefix string for
     /// 
     /// #Return
     /// 
     /// The formatted uri for the api 
-    /// (e.g. https://na1.api.riotgames.com)
-    fn region_uri(region : Region)->String {
-        format!("https://{:?}.api.riotgames.com", region)
+    /// (e.g. https://na1.api.riotgames.com or https://americas.api.riotgames.com)
+    fn host_uri(inner : &ContextInner, host : Host)->String {
+        inner.base_uri.replace("{host}", host.host())
     }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use super::{Context, Region};
+    use super::{Context, Region, Service, ErrorKind, MatchEventDto, MatchlistFilter, RateLimitType, RetryPolicy, CacheStats};
+    use super::summoner_v4;
+    use super::league_v4::{Queue, Tier, Division};
+    use super::transport::{MockTransport, MockResponse};
+    use futures::StreamExt;
+    use reqwest::StatusCode;
+    use std::sync::Arc;
     use tokio::runtime::Runtime;
+    use tokio::time::Duration;
     use crate::util::get_key;
 
+    const SUMMONER_JSON : &str = r#"{"accountId":"acc-1","profileIconId":1,"revisionDate":0,"name":"hi","id":"sum-1","puuid":"puuid-1","summonerLevel":30}"#;
+    const MATCHLIST_JSON : &str = r#"{"startIndex":0,"totalGames":1,"endIndex":1,"matches":[{"gameId":1234,"role":"SOLO","season":13,"platformId":"NA1","champion":1,"queue":420,"lane":"TOP","timestamp":0}]}"#;
+    const MATCH_JSON : &str = r#"{"gameId":1234,"participantIdentities":[{"participantId":1,"player":{"accountId":"acc-1","summonerName":"hi"}}],"participants":[{"participantId":1,"championId":1,"teamId":100}]}"#;
+    const LEAGUE_LIST_JSON : &str = r#"{"leagueId":"league-1","tier":"CHALLENGER","name":"Mock's Champions","queue":"RANKED_SOLO_5x5","entries":[{"summonerId":"sum-2","summonerName":"MockTop","leaguePoints":1200,"rank":"I","wins":300,"losses":250,"veteran":true,"inactive":false,"freshBlood":false,"hotStreak":false}]}"#;
+    const LEAGUE_ENTRIES_JSON : &str = r#"[{"leagueId":"league-2","queueType":"RANKED_SOLO_5x5","tier":"GOLD","rank":"II","summonerId":"sum-1","summonerName":"hi","leaguePoints":100,"wins":60,"losses":55,"veteran":false,"inactive":false,"freshBlood":true,"hotStreak":false,"miniSeries":{"target":2,"wins":1,"losses":1,"progress":"WLN"}}]"#;
+    const CURRENT_GAME_JSON : &str = r#"{"gameId":1234,"gameType":"MATCHED_GAME","gameStartTime":1590000000000,"mapId":11,"gameLength":300,"platformId":"NA1","gameMode":"CLASSIC","gameQueueConfigId":420,"bannedChampions":[{"pickTurn":1,"championId":157,"teamId":100}],"observers":{"encryptionKey":"key-1"},"participants":[{"championId":1,"profileIconId":1,"bot":false,"teamId":100,"summonerName":"hi","summonerId":"sum-1","spell1Id":4,"spell2Id":12,"perks":{"perkIds":[8010,9111],"perkStyle":8000,"perkSubStyle":8400},"gameCustomizationObjects":[]}]}"#;
+    const FEATURED_GAMES_JSON : &str = r#"{"clientRefreshInterval":300,"gameList":[{"gameId":1235,"gameMode":"CLASSIC","gameLength":60,"mapId":11,"gameType":"MATCHED_GAME","gameQueueConfigId":420,"gameStartTime":1590000000000,"platformId":"NA1","bannedChampions":[],"observers":{"encryptionKey":"key-2"},"participants":[{"bot":false,"spell1Id":4,"spell2Id":14,"profileIconId":2,"summonerName":"MockMid","championId":103,"teamId":200}]}]}"#;
+    const ACCOUNT_JSON : &str = r#"{"puuid":"puuid-1","gameName":"hi","tagLine":"NA1"}"#;
+    const MASTERY_JSON : &str = r#"{"championId":103,"championLevel":7,"championPoints":123456,"championPointsSinceLastLevel":101856,"championPointsUntilNextLevel":0,"chestGranted":true,"lastPlayTime":1590000000000,"summonerId":"sum-1","tokensEarned":0}"#;
+    const TIMELINE_JSON : &str = r#"{"frameInterval":60000,"frames":[
+        {"timestamp":0,"participantFrames":{"1":{"participantId":1,"totalGold":500,"xp":0,"position":{"x":554,"y":581}}},"events":[]},
+        {"timestamp":600021,"participantFrames":{"1":{"participantId":1,"totalGold":3850,"xp":4200}},"events":[
+            {"type":"ITEM_PURCHASED","timestamp":590000,"participantId":1,"itemId":1055},
+            {"type":"ELITE_MONSTER_KILL","timestamp":595000,"killerId":1,"monsterType":"DRAGON","monsterSubType":"FIRE_DRAGON","position":{"x":9866,"y":4414}},
+            {"type":"SOME_NEW_EVENT","timestamp":596000}]},
+        {"timestamp":900017,"participantFrames":{"1":{"participantId":1,"totalGold":6120,"xp":7400}},"events":[
+            {"type":"BUILDING_KILL","timestamp":880000,"killerId":1,"teamId":200,"buildingType":"TOWER_BUILDING","laneType":"MID_LANE","towerType":"OUTER_TURRET"}]}]}"#;
+
     /// A test to query each method of each implemented
     /// service and simply check that the structs received
     /// from the server deserialize properly
@@ -567,7 +1662,7 @@ mod tests {
 
         rt.block_on(async {
             for _ in 0..121 { // rate limit on the 120 bucket
-                let dto = ctx.query_summoner_v4_by_summoner_name(Region::Na1, "hi", 3).await;
+                let dto = ctx.query_summoner_v4_by_summoner_name(Region::Na1, "hi").await;
                 assert!(dto.is_ok());
             }
         });
@@ -604,8 +1699,8 @@ mod tests {
 
                 //issue to concurrent requests for a resource
                 let (dto1, dto2) = tokio::join!(
-                    ctx.query_summoner_v4_by_summoner_name(Region::Na1, "hi", 3),
-                    ctx.query_summoner_v4_by_summoner_name(Region::Na1, "hi", 3)
+                    ctx.query_summoner_v4_by_summoner_name(Region::Na1, "hi"),
+                    ctx.query_summoner_v4_by_summoner_name(Region::Na1, "hi")
                 );
 
                 assert!(dto1.is_ok());
@@ -613,4 +1708,805 @@ mod tests {
             });
         }
     }
-}
\ No newline at end of file
+
+    /// Same as `test_query_struct_deserialization`, but against
+    /// canned responses so it can run without an api key
+    #[test]
+    fn test_mock_query_struct_deserialization() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/summoner/v4/summoners/by-name/hi", MockResponse::json(SUMMONER_JSON));
+        transport.push_response("/lol/summoner/v4/summoners/by-account/acc-1", MockResponse::json(SUMMONER_JSON));
+        transport.push_response("/lol/summoner/v4/summoners/by-puuid/puuid-1", MockResponse::json(SUMMONER_JSON));
+        transport.push_response("/lol/summoner/v4/summoners/sum-1", MockResponse::json(SUMMONER_JSON));
+        transport.push_response("/lol/match/v4/matchlists/by-account/acc-1", MockResponse::json(MATCHLIST_JSON));
+        transport.push_response("/lol/match/v4/matches/1234", MockResponse::json(MATCH_JSON));
+        let ctx = Context::with_transport("test-key", transport.clone());
+
+        rt.block_on(async {
+            let summoner_dto = ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "hi").await.unwrap();
+            assert_eq!(summoner_dto.account_id, "acc-1");
+
+            let summoner_dto = ctx.try_query_summoner_v4_by_account(Region::Na1, "acc-1").await.unwrap();
+            assert_eq!(summoner_dto.puuid, "puuid-1");
+
+            let summoner_dto = ctx.try_query_summoner_v4_by_puuid(Region::Na1, "puuid-1").await.unwrap();
+            assert_eq!(summoner_dto.id, "sum-1");
+
+            let summoner_dto = ctx.try_query_summoner_v4_by_summoner_id(Region::Na1, "sum-1").await.unwrap();
+            assert_eq!(summoner_dto.account_id, "acc-1");
+
+            let matchlist_dto = ctx.try_query_match_v4_matchlist_by_account(Region::Na1, "acc-1").await.unwrap();
+            assert_eq!(matchlist_dto.matches[0].game_id, 1234);
+
+            let match_dto = ctx.try_query_match_v4_match_by_id(Region::Na1, 1234).await.unwrap();
+            assert_eq!(match_dto.participant_identities[0].player.account_id, "acc-1");
+        });
+
+        assert_eq!(transport.requests().len(), 6);
+    }
+
+    /// Checks a matchlist filter is sent as the query string
+    #[test]
+    fn test_mock_matchlist_filter() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/match/v4/matchlists/by-account/acc-1?queue=420&season=13&beginTime=1590000000000&beginIndex=0&endIndex=100",
+                                MockResponse::json(MATCHLIST_JSON));
+        let ctx = Context::with_transport("test-key", transport.clone());
+
+        let filter = MatchlistFilter {
+            queue : vec![420],
+            season : vec![13],
+            begin_time : Some(1590000000000),
+            begin_index : Some(0),
+            end_index : Some(100),
+            ..MatchlistFilter::default()
+        };
+
+        rt.block_on(async {
+            let matchlist_dto = ctx.try_query_match_v4_matchlist_by_account_with_filter(Region::Na1, "acc-1", &filter).await.unwrap();
+            assert_eq!(matchlist_dto.matches[0].queue, 420);
+        });
+
+        assert_eq!(MatchlistFilter::default().query_string(), "");
+    }
+
+    /// Checks the matchlist stream walks every page of the
+    /// history and stops at the filter's bounds
+    #[test]
+    fn test_mock_matchlist_stream() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/match/v4/matchlists/by-account/acc-1?beginIndex=0&endIndex=100", MockResponse::json(
+            r#"{"startIndex":0,"endIndex":100,"totalGames":150,"matches":[{"gameId":3,"role":"SOLO","season":13,"platformId":"NA1","champion":1,"queue":420,"lane":"TOP","timestamp":3000},{"gameId":2,"role":"SOLO","season":13,"platformId":"NA1","champion":1,"queue":420,"lane":"TOP","timestamp":2000}]}"#));
+        transport.push_response("/lol/match/v4/matchlists/by-account/acc-1?beginIndex=100&endIndex=200", MockResponse::json(
+            r#"{"startIndex":100,"endIndex":150,"totalGames":150,"matches":[{"gameId":1,"role":"SOLO","season":13,"platformId":"NA1","champion":1,"queue":420,"lane":"TOP","timestamp":1000}]}"#));
+        transport.push_response("/lol/match/v4/matchlists/by-account/acc-1?beginTime=1500&beginIndex=0&endIndex=100", MockResponse::json(
+            r#"{"startIndex":0,"endIndex":100,"totalGames":150,"matches":[{"gameId":3,"role":"SOLO","season":13,"platformId":"NA1","champion":1,"queue":420,"lane":"TOP","timestamp":3000},{"gameId":2,"role":"SOLO","season":13,"platformId":"NA1","champion":1,"queue":420,"lane":"TOP","timestamp":2000},{"gameId":1,"role":"SOLO","season":13,"platformId":"NA1","champion":1,"queue":420,"lane":"TOP","timestamp":1000}]}"#));
+        let ctx = Context::with_transport("test-key", transport.clone());
+
+        rt.block_on(async {
+            let matches : Vec<_> = ctx.match_v4_matchlist_stream_by_account(Region::Na1, "acc-1", &MatchlistFilter::default())
+                                      .map(|match_ref| match_ref.unwrap().game_id)
+                                      .collect().await;
+            assert_eq!(matches, vec![3, 2, 1]);
+
+            let filter = MatchlistFilter { begin_time : Some(1500), ..MatchlistFilter::default() };
+            let matches : Vec<_> = ctx.match_v4_matchlist_stream_by_account(Region::Na1, "acc-1", &filter)
+                                      .map(|match_ref| match_ref.unwrap().game_id)
+                                      .collect().await;
+            assert_eq!(matches, vec![3, 2]);
+
+            let mut errors = Box::pin(ctx.match_v4_matchlist_stream_by_account(Region::Na1, "acc-2", &MatchlistFilter::default()));
+            assert!(errors.next().await.unwrap().is_err());
+            assert!(errors.next().await.is_none());
+        });
+
+        assert_eq!(transport.requests().len(), 4);
+    }
+
+    /// Checks the league-v4 queries hit the right paths and
+    /// deserialize the ladder and entries
+    #[test]
+    fn test_mock_league_queries() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/league/v4/challengerleagues/by-queue/RANKED_SOLO_5x5", MockResponse::json(LEAGUE_LIST_JSON));
+        transport.push_response("/lol/league/v4/entries/RANKED_SOLO_5x5/GOLD/II?page=2", MockResponse::json(LEAGUE_ENTRIES_JSON));
+        transport.push_response("/lol/league/v4/entries/by-summoner/sum-1", MockResponse::json(LEAGUE_ENTRIES_JSON));
+        let ctx = Context::with_transport("test-key", transport.clone());
+
+        rt.block_on(async {
+            let league = ctx.try_query_league_v4_challenger_league(Region::Na1, Queue::RankedSolo5x5).await.unwrap();
+            assert_eq!(league.tier, "CHALLENGER");
+            assert_eq!(league.entries[0].summoner_id, "sum-2");
+            assert!(league.entries[0].mini_series.is_none());
+
+            let entries = ctx.try_query_league_v4_entries(Region::Na1, Queue::RankedSolo5x5, Tier::Gold, Division::II, 2).await.unwrap();
+            assert_eq!(entries[0].rank, "II");
+            assert_eq!(entries[0].mini_series.as_ref().unwrap().progress, "WLN");
+
+            let entries = ctx.try_query_league_v4_entries_by_summoner(Region::Na1, "sum-1").await.unwrap();
+            assert_eq!(entries[0].summoner_id, "sum-1");
+        });
+
+        assert_eq!(transport.requests().len(), 3);
+    }
+
+    /// Checks the spectator-v4 queries deserialize a live game
+    /// and the featured games
+    #[test]
+    fn test_mock_spectator_queries() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/spectator/v4/active-games/by-summoner/sum-1", MockResponse::json(CURRENT_GAME_JSON));
+        transport.push_response("/lol/spectator/v4/featured-games", MockResponse::json(FEATURED_GAMES_JSON));
+        let ctx = Context::with_transport("test-key", transport);
+
+        rt.block_on(async {
+            let game = ctx.try_query_spectator_v4_active_game_by_summoner(Region::Na1, "sum-1").await.unwrap();
+            assert_eq!(game.banned_champions[0].champion_id, 157);
+            assert_eq!(game.observers.encryption_key, "key-1");
+            assert_eq!(game.participants[0].perks.perk_ids, vec![8010, 9111]);
+
+            let featured = ctx.try_query_spectator_v4_featured_games(Region::Na1).await.unwrap();
+            assert_eq!(featured.client_refresh_interval, 300);
+            assert_eq!(featured.game_list[0].participants[0].summoner_name, "MockMid");
+        });
+    }
+
+    /// Checks the champion-mastery-v4 queries hit the right paths
+    /// and deserialize
+    #[test]
+    fn test_mock_champion_mastery_queries() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/champion-mastery/v4/champion-masteries/by-summoner/sum-1", MockResponse::json(&format!("[{}]", MASTERY_JSON)));
+        transport.push_response("/lol/champion-mastery/v4/champion-masteries/by-summoner/sum-1/by-champion/103", MockResponse::json(MASTERY_JSON));
+        transport.push_response("/lol/champion-mastery/v4/scores/by-summoner/sum-1", MockResponse::json("312"));
+        let ctx = Context::with_transport("test-key", transport);
+
+        rt.block_on(async {
+            let masteries = ctx.try_query_champion_mastery_v4_by_summoner(Region::Na1, "sum-1").await.unwrap();
+            assert_eq!(masteries[0].champion_id, 103);
+
+            let mastery = ctx.try_query_champion_mastery_v4_by_summoner_and_champion(Region::Na1, "sum-1", 103).await.unwrap();
+            assert_eq!(mastery.champion_points, 123456);
+
+            let score = ctx.try_query_champion_mastery_v4_score_by_summoner(Region::Na1, "sum-1").await.unwrap();
+            assert_eq!(score, 312);
+        });
+    }
+
+    /// Checks the account-v1 queries hit the regional cluster,
+    /// and that a riot id resolves to the summoner through its puuid
+    #[test]
+    fn test_mock_account_queries() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/americas/riot/account/v1/accounts/by-riot-id/hi/NA1", MockResponse::json(ACCOUNT_JSON));
+        transport.push_response("/americas/riot/account/v1/accounts/by-puuid/puuid-1", MockResponse::json(ACCOUNT_JSON));
+        transport.push_response("/na1/lol/summoner/v4/summoners/by-puuid/puuid-1", MockResponse::json(SUMMONER_JSON));
+        let ctx = Context::builder("test-key")
+            .transport(transport.clone())
+            .base_uri("http://localhost/{host}")
+            .build();
+
+        rt.block_on(async {
+            let account = ctx.try_query_account_v1_by_riot_id(Region::Na1, "hi", "NA1").await.unwrap();
+            assert_eq!(account.puuid, "puuid-1");
+
+            let account = ctx.try_query_account_v1_by_puuid(Region::Na1, &account.puuid).await.unwrap();
+            assert_eq!((account.game_name.as_str(), account.tag_line.as_str()), ("hi", "NA1"));
+
+            let summoner_dto = ctx.try_query_summoner_v4_by_puuid(Region::Na1, &account.puuid).await.unwrap();
+            assert_eq!(summoner_dto.account_id, "acc-1");
+        });
+
+        assert_eq!(super::account_v1::split_riot_id("hi#NA1"), Some(("hi", "NA1")));
+        assert_eq!(super::account_v1::split_riot_id("hi"), None);
+        assert_eq!(super::account_v1::split_riot_id("hi#"), None);
+    }
+
+    /// Checks that a timeline deserializes each event type it knows
+    /// and that the per-minute helpers pick out the right frames
+    #[test]
+    fn test_mock_timeline() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/match/v4/timelines/by-match/1234", MockResponse::json(TIMELINE_JSON));
+        let ctx = Context::with_transport("test-key", transport);
+
+        let timeline = rt.block_on(ctx.try_query_match_v4_timeline_by_match_id(Region::Na1, 1234)).unwrap();
+        assert_eq!(timeline.gold_at(1, 10), Some(3850));
+        assert_eq!(timeline.xp_at(1, 15), Some(7400));
+        assert_eq!(timeline.gold_at(1, 20), None);
+        assert_eq!(timeline.gold_at(2, 10), None);
+        assert_eq!(timeline.events().count(), 4);
+        assert!(timeline.events().any(|event| matches!(event, MatchEventDto::Unknown)));
+
+        let objective_times : Vec<Option<i64>> = timeline.objective_events().map(|event| event.timestamp()).collect();
+        assert_eq!(objective_times, vec![Some(595000), Some(880000)]);
+        let first_objective = timeline.objective_events().next();
+        match first_objective {
+            Some(MatchEventDto::EliteMonsterKill(event)) => assert_eq!(event.monster_sub_type, "FIRE_DRAGON"),
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    /// Checks that a response whose rate-limit counts have reached
+    /// the limit puts the endpoint on cooldown, so the next query
+    /// never reaches the transport.
+    #[test]
+    fn test_mock_full_bucket_cooldown() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        let path = "/lol/summoner/v4/summoners/by-name/hi";
+        transport.push_response(path, MockResponse::json(SUMMONER_JSON)
+            .header("Date", "Tue, 01 Jan 2030 00:00:00 GMT")
+            .header("X-App-Rate-Limit", "2:10")
+            .header("X-App-Rate-Limit-Count", "1:10"));
+        transport.push_response(path, MockResponse::json(SUMMONER_JSON)
+            .header("Date", "Tue, 01 Jan 2030 00:00:01 GMT")
+            .header("X-App-Rate-Limit", "2:10")
+            .header("X-App-Rate-Limit-Count", "2:10"));
+        let ctx = Context::builder("test-key")
+            .transport(transport.clone())
+            .wait_for_capacity(false)
+            .build();
+
+        rt.block_on(async {
+            assert!(ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "hi").await.is_ok());
+
+            // the response that fills the bucket still goes through
+            assert!(ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "hi").await.is_ok());
+
+            // but the next query is refused without being sent
+            let err = ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "hi").await.unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::EndpointNotReady(_)));
+            assert!(err.can_retry());
+            assert!(err.retry_time().unwrap() <= tokio::time::Duration::from_secs(10));
+        });
+
+        assert_eq!(transport.request_count(path), 2);
+    }
+
+    /// Checks that queries wait for room in the rate limits rather
+    /// than failing once a bucket is full
+    #[test]
+    fn test_mock_full_bucket_waits() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        let names = ["a", "b", "c"];     // distinct queries, so none of them are coalesced
+        let counts = ["1:1", "2:1", "1:1"];     // the third query lands in a new window
+        for (name, count) in names.iter().zip(counts.iter()) {
+            transport.push_response(&format!("/lol/summoner/v4/summoners/by-name/{}", name), MockResponse::json(SUMMONER_JSON)
+                .header("X-App-Rate-Limit", "2:1")
+                .header("X-App-Rate-Limit-Count", count));
+        }
+        let ctx = Context::builder("test-key")
+            .transport(transport.clone())
+            .app_rate_limits(&[(2, 1)])
+            .build();
+
+        rt.block_on(async {
+            let start = std::time::Instant::now();
+            let (r1, r2, r3) = tokio::join!(
+                ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, names[0]),
+                ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, names[1]),
+                ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, names[2]));
+            assert!(r1.is_ok() && r2.is_ok() && r3.is_ok());
+
+            // the third query had to wait for the window to end
+            assert!(start.elapsed() >= std::time::Duration::from_millis(900));
+        });
+
+        assert_eq!(transport.requests().len(), 3);
+    }
+
+    /// Checks that a 429 with no prior knowledge of the rate limits
+    /// forces a cooldown on the endpoints
+    #[test]
+    fn test_mock_429_forces_cooldown() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        let path = "/lol/match/v4/matches/1234";
+        transport.push_response(path, MockResponse::error(StatusCode::TOO_MANY_REQUESTS));
+        let ctx = Context::builder("test-key")
+            .transport(transport.clone())
+            .wait_for_capacity(false)
+            .build();
+
+        rt.block_on(async {
+            assert!(ctx.try_query_match_v4_match_by_id(Region::Na1, 1234).await.is_err());
+
+            let err = ctx.try_query_match_v4_match_by_id(Region::Na1, 1234).await.unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::EndpointNotReady(_)));
+        });
+
+        assert_eq!(transport.request_count(path), 1);
+    }
+
+    /// Checks that a 429 only cools down the layer named by its
+    /// `X-Rate-Limit-Type` header, for as long as its `Retry-After` says
+    #[test]
+    fn test_mock_429_retry_after() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        let path = "/lol/match/v4/matches/1234";
+        transport.push_response(path, MockResponse::error(StatusCode::TOO_MANY_REQUESTS)
+            .header("Retry-After", "1")
+            .header("X-Rate-Limit-Type", "service"));
+        transport.push_response(path, MockResponse::json(MATCH_JSON));
+        transport.push_response("/lol/summoner/v4/summoners/by-name/hi", MockResponse::json(SUMMONER_JSON));
+        let ctx = Context::builder("test-key")
+            .transport(transport.clone())
+            .wait_for_capacity(false)
+            .build();
+
+        rt.block_on(async {
+            let err = ctx.try_query_match_v4_match_by_id(Region::Na1, 1234).await.unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::RateLimited(Some(RateLimitType::Service), _)));
+            assert_eq!(err.retry_time(), Some(tokio::time::Duration::from_secs(1)));
+
+            // the match service is cooling down, but the application limits aren't
+            assert!(matches!(ctx.try_query_match_v4_match_by_id(Region::Na1, 1234).await.unwrap_err().kind(), ErrorKind::EndpointNotReady(_)));
+            assert!(ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "hi").await.is_ok());
+
+            // and a retrying query waits out the cooldown
+            assert!(ctx.query_match_v4_match_by_id(Region::Na1, 1234).await.is_ok());
+        });
+
+        assert_eq!(transport.request_count(path), 2);
+    }
+
+    /// Checks that server errors, timeouts and connection errors are
+    /// retried with backoff, unless the retry policy says otherwise
+    #[test]
+    fn test_mock_retry_policy() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        let path = "/lol/match/v4/matches/1234";
+        transport.push_response(path, MockResponse::error(StatusCode::SERVICE_UNAVAILABLE));
+        transport.push_response(path, MockResponse::timeout());
+        transport.push_response(path, MockResponse::connection_error());
+        transport.push_response(path, MockResponse::json(MATCH_JSON));
+        let policy = RetryPolicy {
+            initial_backoff : Duration::from_millis(10),
+            ..RetryPolicy::default()
+        };
+        let ctx = Context::builder("test-key")
+            .transport(transport.clone())
+            .retry_policy(policy.clone())
+            .build();
+
+        assert!(rt.block_on(ctx.query_match_v4_match_by_id(Region::Na1, 1234)).is_ok());
+        assert_eq!(transport.request_count(path), 4);
+
+        // the same failures are fatal once the policy stops retrying them
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response(path, MockResponse::error(StatusCode::BAD_GATEWAY));
+        transport.push_response(path, MockResponse::json(MATCH_JSON));
+        let ctx = Context::builder("test-key")
+            .transport(transport.clone())
+            .retry_policy(RetryPolicy { retry_server_errors : false, ..policy })
+            .build();
+
+        let err = rt.block_on(ctx.query_match_v4_match_by_id(Region::Na1, 1234)).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::ResponseStatus(StatusCode::BAD_GATEWAY)));
+        assert_eq!(transport.request_count(path), 1);
+    }
+
+    /// Checks that the default transport gives up on a server
+    /// which accepts the connection but never answers
+    #[test]
+    fn test_request_timeout() {
+
+        let mut rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                let mut hung = Vec::new();
+                while let Ok((stream, _)) = listener.accept().await {
+                    hung.push(stream);
+                }
+            });
+
+            let ctx = Context::builder("test-key")
+                .base_uri(&format!("http://{}", addr))
+                .request_timeout(Duration::from_millis(200))
+                .build();
+            let err = ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "hi").await.unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::Timeout(_)));
+        });
+    }
+
+    /// Checks that unsuccessful responses get the error kind
+    /// of their status, along with the uri and riot error body
+    #[test]
+    fn test_mock_status_error_kinds() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/summoner/v4/summoners/by-name/bad", MockResponse::error(StatusCode::BAD_REQUEST));
+        transport.push_response("/lol/summoner/v4/summoners/by-name/expired", MockResponse::error(StatusCode::FORBIDDEN));
+        transport.push_response("/lol/summoner/v4/summoners/by-name/media", MockResponse::error(StatusCode::UNSUPPORTED_MEDIA_TYPE));
+        let ctx = Context::with_transport("test-key", transport.clone());
+
+        rt.block_on(async {
+            let err = ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "nobody").await.unwrap_err();
+            match err.kind() {
+                ErrorKind::NotFound(status, uri, body) => {
+                    assert_eq!(*status, StatusCode::NOT_FOUND);
+                    assert!(uri.ends_with("/lol/summoner/v4/summoners/by-name/nobody"));
+                    assert!(body.contains("\"status_code\":404"));
+                },
+                kind => panic!("expected NotFound, got {:?}", kind),
+            }
+            assert_eq!(err.status(), Some(StatusCode::NOT_FOUND));
+
+            let err = ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "bad").await.unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::BadRequest(StatusCode::BAD_REQUEST, _, _)));
+            let err = ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "expired").await.unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::Unauthorized(StatusCode::FORBIDDEN, _, _)));
+            let err = ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "media").await.unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::UnsupportedMediaType(StatusCode::UNSUPPORTED_MEDIA_TYPE, _, _)));
+        });
+    }
+
+    /// Checks that cached matches are served from disk without
+    /// sending a query or touching the rate limits
+    #[test]
+    fn test_mock_disk_cache() {
+
+        let mut rt = Runtime::new().unwrap();
+        let dir = std::env::temp_dir().join(format!("lol-context-cache-test-{}", rand::random::<u64>()));
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/match/v4/matches/1234", MockResponse::json(MATCH_JSON));
+        transport.push_response("/lol/match/v4/timelines/by-match/1234", MockResponse::json(TIMELINE_JSON));
+        let ctx = Context::builder("test-key")
+            .transport(transport.clone())
+            .disk_cache(&dir)
+            .build();
+
+        rt.block_on(async {
+            ctx.try_query_match_v4_match_by_id(Region::Na1, 1234).await.unwrap();
+            ctx.try_query_match_v4_timeline_by_match_id(Region::Na1, 1234).await.unwrap();
+        });
+        assert_eq!(transport.requests().len(), 2);
+
+        // a fresh context (e.g. a rerun of the crawl) never sends the queries
+        let transport = Arc::new(MockTransport::new());
+        let ctx = Context::builder("test-key")
+            .transport(transport.clone())
+            .disk_cache(&dir)
+            .build();
+
+        rt.block_on(async {
+            let match_dto = ctx.try_query_match_v4_match_by_id(Region::Na1, 1234).await.unwrap();
+            assert_eq!(match_dto.game_id, 1234);
+            let timeline = ctx.try_query_match_v4_timeline_by_match_id(Region::Na1, 1234).await.unwrap();
+            assert_eq!(timeline.frames.len(), 3);
+            assert!(ctx.try_query_match_v4_match_by_id(Region::Euw1, 1234).await.is_err());
+            assert_eq!(ctx.inner.keys[0].endpoints.lock().await.len(), 3);
+        });
+        assert_eq!(transport.requests(), vec!["/lol/match/v4/matches/1234".to_string()]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Checks that bodies which don't deserialize are never cached,
+    /// and that a bad body already in the cache is queried again
+    #[test]
+    fn test_mock_disk_cache_skips_bad_bodies() {
+
+        let mut rt = Runtime::new().unwrap();
+        let dir = std::env::temp_dir().join(format!("lol-context-cache-test-{}", rand::random::<u64>()));
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/match/v4/matches/1234", MockResponse::json(r#"{"gameId":12"#));
+        transport.push_response("/lol/match/v4/matches/1234", MockResponse::json(MATCH_JSON));
+        let ctx = Context::builder("test-key")
+            .transport(transport.clone())
+            .disk_cache(&dir)
+            .build();
+
+        rt.block_on(async {
+            // the truncated body fails the query without being cached
+            assert!(ctx.try_query_match_v4_match_by_id(Region::Na1, 1234).await.is_err());
+            assert_eq!(ctx.try_query_match_v4_match_by_id(Region::Na1, 1234).await.unwrap().game_id, 1234);
+
+            // a bad body left in the cache (e.g. by an older version) is replaced
+            let cached = dir.join("na1/lol/match/v4/matches/1234.json");
+            std::fs::write(&cached, "[]").unwrap();
+            assert_eq!(ctx.try_query_match_v4_match_by_id(Region::Na1, 1234).await.unwrap().game_id, 1234);
+            assert!(std::fs::read_to_string(&cached).unwrap().contains("1234"));
+        });
+        assert_eq!(transport.request_count("/lol/match/v4/matches/1234"), 3);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Checks that repeated lookups are served from the in-memory
+    /// cache, and that the stats count them
+    #[test]
+    fn test_mock_memory_cache() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/summoner/v4/summoners/by-name/hi", MockResponse::json(SUMMONER_JSON));
+        transport.push_response("/lol/match/v4/matches/1234", MockResponse::json(MATCH_JSON));
+        let ctx = Context::builder("test-key")
+            .transport(transport.clone())
+            .memory_cache(10)
+            .memory_cache_ttl(Service::SummonerV4, summoner_v4::Method::ByAccount as u32, Duration::from_secs(0))
+            .build();
+
+        rt.block_on(async {
+            for _ in 0..3 {
+                assert_eq!(ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "hi").await.unwrap().account_id, "acc-1");
+                assert!(ctx.try_query_match_v4_match_by_id(Region::Na1, 1234).await.is_ok());
+            }
+            assert!(ctx.try_query_summoner_v4_by_summoner_name(Region::Euw1, "hi").await.is_ok());
+            assert!(ctx.try_query_summoner_v4_by_account(Region::Na1, "acc-1").await.is_err());
+            assert!(ctx.try_query_summoner_v4_by_account(Region::Na1, "acc-1").await.is_err());
+        });
+
+        // matches and disabled methods aren't cached, and each region is its own lookup
+        assert_eq!(transport.request_count("/lol/summoner/v4/summoners/by-name/hi"), 2);
+        assert_eq!(transport.request_count("/lol/match/v4/matches/1234"), 3);
+        assert_eq!(transport.request_count("/lol/summoner/v4/summoners/by-account/acc-1"), 2);
+        assert_eq!(ctx.memory_cache_stats(), CacheStats { hits : 2, misses : 2, evictions : 0 });
+    }
+
+    /// Checks that identical queries in flight at the same time
+    /// share a single request
+    #[test]
+    fn test_mock_coalesced_queries() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/match/v4/matches/1234", MockResponse::json(MATCH_JSON).delay(Duration::from_millis(100)));
+        transport.push_response("/lol/match/v4/matches/1", MockResponse::error(StatusCode::NOT_FOUND).delay(Duration::from_millis(100)));
+        let ctx = Context::with_transport("test-key", transport.clone());
+
+        rt.block_on(async {
+            let queries = (0..4).map(|_| ctx.try_query_match_v4_match_by_id(Region::Na1, 1234));
+            for match_dto in futures::future::join_all(queries).await {
+                assert_eq!(match_dto.unwrap().game_id, 1234);
+            }
+
+            // unsuccessful responses are shared too, each as its own error
+            let queries = (0..4).map(|_| ctx.try_query_match_v4_match_by_id(Region::Na1, 1));
+            for err in futures::future::join_all(queries).await {
+                assert!(matches!(err.unwrap_err().kind(), ErrorKind::NotFound(..)));
+            }
+
+            // nothing stays in flight, so later queries are sent again
+            assert!(ctx.inner.in_flight.lock().unwrap().is_empty());
+            assert!(ctx.try_query_match_v4_match_by_id(Region::Na1, 1234).await.is_ok());
+        });
+
+        assert_eq!(transport.request_count("/lol/match/v4/matches/1234"), 2);
+        assert_eq!(transport.request_count("/lol/match/v4/matches/1"), 1);
+    }
+
+    /// Checks that queries are spread across a pool of keys, each
+    /// with its own application limits
+    #[test]
+    fn test_mock_api_key_pool() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        let names = ["a", "b", "c", "d"];
+        for name in names.iter() {
+            transport.push_response(&format!("/lol/summoner/v4/summoners/by-name/{}", name), MockResponse::json(SUMMONER_JSON)
+                .header("X-App-Rate-Limit", "2:1")
+                .header("X-App-Rate-Limit-Count", "1:1")
+                .delay(Duration::from_millis(20)));
+        }
+        let ctx = Context::builder("key-1")
+            .add_api_key("key-2")
+            .transport(transport.clone())
+            .app_rate_limits(&[(2, 1)])
+            .build();
+
+        rt.block_on(async {
+            // four queries fit in the two keys' buckets at once, rather than waiting out a window
+            let start = std::time::Instant::now();
+            let queries = names.iter().map(|name| ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, name));
+            for summoner in futures::future::join_all(queries).await {
+                assert!(summoner.is_ok());
+            }
+            assert!(start.elapsed() < std::time::Duration::from_millis(900));
+        });
+
+        assert_eq!(transport.api_key_count("key-1"), 2);
+        assert_eq!(transport.api_key_count("key-2"), 2);
+    }
+
+    /// Checks that the rate-limit state is saved when a context is
+    /// dropped and restored by the next context using the same file
+    #[test]
+    fn test_mock_state_file() {
+
+        let mut rt = Runtime::new().unwrap();
+        let path = std::env::temp_dir().join(format!("lol-state-test-{}.json", rand::random::<u64>()));
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/summoner/v4/summoners/by-name/hi", MockResponse::json(SUMMONER_JSON)
+            .header("X-App-Rate-Limit", "1:10")
+            .header("X-App-Rate-Limit-Count", "1:10"));
+
+        let ctx = Context::builder("test-key")
+            .transport(transport.clone())
+            .state_file(&path)
+            .build();
+        rt.block_on(async {
+            assert!(ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "hi").await.is_ok());
+        });
+        drop(ctx);
+        assert!(path.exists());
+
+        // the window the last context filled is still full
+        let ctx = Context::builder("test-key")
+            .transport(transport.clone())
+            .state_file(&path)
+            .wait_for_capacity(false)
+            .build();
+        rt.block_on(async {
+            let err = ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "hi").await.unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::EndpointNotReady(..)));
+        });
+        drop(ctx);
+
+        // while another key starts from scratch
+        let ctx = Context::builder("other-key")
+            .transport(transport.clone())
+            .state_file(&path)
+            .wait_for_capacity(false)
+            .build();
+        rt.block_on(async {
+            assert!(ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "hi").await.is_ok());
+        });
+        drop(ctx);
+
+        assert_eq!(transport.request_count("/lol/summoner/v4/summoners/by-name/hi"), 2);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// Checks that contexts sharing a coordinator stay under the
+    /// application limits together, like separate processes would
+    #[test]
+    fn test_mock_coordinated_contexts() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        for name in ["a", "b"].iter() {
+            transport.push_response(&format!("/lol/summoner/v4/summoners/by-name/{}", name), MockResponse::json(SUMMONER_JSON)
+                .header("X-App-Rate-Limit", "1:10")
+                .header("X-App-Rate-Limit-Count", "1:10"));
+        }
+        let path = std::env::temp_dir().join(format!("lol-coordinator-test-{}.sock", rand::random::<u64>()));
+
+        rt.block_on(async {
+            super::coordinator::spawn(&path).await.unwrap();
+            let new_ctx = |key : &str| Context::builder(key)
+                .transport(transport.clone())
+                .coordinator(&path)
+                .wait_for_capacity(false)
+                .build();
+            let (ctx_1, ctx_2, ctx_other_key) = (new_ctx("test-key"), new_ctx("test-key"), new_ctx("other-key"));
+
+            // the first context fills the window for both of them
+            assert!(ctx_1.try_query_summoner_v4_by_summoner_name(Region::Na1, "a").await.is_ok());
+            let err = ctx_2.try_query_summoner_v4_by_summoner_name(Region::Na1, "b").await.unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::EndpointNotReady(..)));
+
+            // but not for a different key
+            assert!(ctx_other_key.try_query_summoner_v4_by_summoner_name(Region::Na1, "b").await.is_ok());
+        });
+
+        assert_eq!(transport.requests().len(), 2);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// Checks that a query which went through still succeeds when
+    /// the coordinator can't take the counts from its response
+    #[test]
+    fn test_mock_coordinator_update_failure() {
+
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/summoner/v4/summoners/by-name/hi", MockResponse::json(SUMMONER_JSON)
+            .header("X-App-Rate-Limit", "20:1")
+            .header("X-App-Rate-Limit-Count", "1:1"));
+        let path = std::env::temp_dir().join(format!("lol-coordinator-test-{}.sock", rand::random::<u64>()));
+
+        rt.block_on(async {
+            // a coordinator which reserves every query, but drops the connection on any update
+            let mut listener = tokio::net::UnixListener::bind(&path).unwrap();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(async move {
+                        let mut stream = BufReader::new(stream);
+                        let mut line = String::new();
+                        while stream.read_line(&mut line).await.unwrap_or(0) > 0 && line.contains("Reserve") {
+                            stream.write_all(b"\"Reserved\"\n").await.unwrap();
+                            line.clear();
+                        }
+                    });
+                }
+            });
+
+            let ctx = Context::builder("test-key")
+                .transport(transport.clone())
+                .coordinator(&path)
+                .build();
+            let summoner = ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "hi").await.unwrap();
+            assert_eq!(summoner.name, "hi");
+        });
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// Checks that each region has its own method limits, so
+    /// a full method bucket on one host doesn't hold up the others
+    #[test]
+    fn test_mock_method_limits_per_region() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/lol/summoner/v4/summoners/by-name/hi", MockResponse::json(SUMMONER_JSON)
+            .header("X-Method-Rate-Limit", "1:10")
+            .header("X-Method-Rate-Limit-Count", "1:10"));
+        let ctx = Context::builder("test-key")
+            .transport(transport.clone())
+            .wait_for_capacity(false)
+            .build();
+
+        rt.block_on(async {
+            assert!(ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "hi").await.is_ok());
+            assert!(ctx.try_query_summoner_v4_by_summoner_name(Region::Euw1, "hi").await.is_ok());
+
+            let err = ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "hi").await.unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::EndpointNotReady(..)));
+        });
+
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    /// Checks that each region's queries are sent to its own host
+    #[test]
+    fn test_region_hosts() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response("/euw1/lol/summoner/v4/summoners/by-name/hi", MockResponse::json(SUMMONER_JSON));
+        transport.push_response("/kr/lol/summoner/v4/summoners/by-name/hi", MockResponse::json(SUMMONER_JSON));
+        let ctx = Context::builder("test-key")
+            .transport(transport.clone())
+            .base_uri("http://localhost/{host}")
+            .build();
+
+        rt.block_on(async {
+            assert!(ctx.try_query_summoner_v4_by_summoner_name(Region::Euw1, "hi").await.is_ok());
+            assert!(ctx.try_query_summoner_v4_by_summoner_name(Region::Kr, "hi").await.is_ok());
+        });
+
+        assert_eq!("la2".parse::<Region>().unwrap(), Region::La2);
+        assert_eq!(Region::Oc1.host(), "oc1");
+    }
+}
@@ -0,0 +1,106 @@
+//! Walks a player's whole match history one matchlist
+//! page at a time, so callers can treat it as a single
+//! `Stream` of match references instead of hand-rolling
+//! the `beginIndex`/`endIndex` loop around `MatchlistDto`.
+
+// external uses
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+// my mods/uses
+use super::{Context, ContextInner, Region, Result};
+use super::services::match_v4::{MatchlistFilter, MatchReferenceDto};
+
+/// The most matches the api serves in a single matchlist page
+const MATCHLIST_PAGE_SIZE : i32 = 100;
+
+/// The state of a matchlist stream between pages
+pub(super) struct MatchlistPager {
+    inner : Arc<ContextInner>,
+    region : Region,
+    encrypted_account_id : String,
+    filter : MatchlistFilter,
+    next_index : Option<i32>,               // begin index of the next page to fetch, None once the history ran out
+    page : VecDeque<MatchReferenceDto>,     // the fetched matches which haven't been handed out yet
+}
+
+impl MatchlistPager {
+
+    /// ctor - the first page fetched starts at the filter's
+    /// `begin_index` (or the most recent match if it's unset)
+    pub(super) fn new(
+        inner : Arc<ContextInner>, region : Region, encrypted_account_id : &str,
+        filter : &MatchlistFilter) -> MatchlistPager {
+
+        MatchlistPager {
+            inner,
+            region,
+            encrypted_account_id : encrypted_account_id.to_string(),
+            filter : filter.clone(),
+            next_index : Some(filter.begin_index.unwrap_or(0)),
+            page : VecDeque::new(),
+        }
+    }
+
+    /// Gets the next match of the history, fetching the
+    /// next page through the context's rate limiting if
+    /// the current one is used up.
+    ///
+    /// # Return
+    ///
+    /// `Some(Ok(match_ref))` for the next match, `Some(Err(e))` if a page
+    /// couldn't be fetched (after which the history ends), or `None` once
+    /// the history ran out, the filter's `end_index` was reached or a
+    /// match is older than the filter's `begin_time`
+    pub(super) async fn next(&mut self) -> Option<Result<MatchReferenceDto>> {
+
+        loop {
+            if let Some(match_ref) = self.page.pop_front() {
+
+                // matches come newest first, so every match after this one is out of bounds too
+                if self.filter.begin_time.is_some_and(|begin_time| match_ref.timestamp < begin_time) {
+                    self.next_index = None;
+                    self.page.clear();
+                    return None;
+                }
+                return Some(Ok(match_ref));
+            }
+
+            let begin_index = self.next_index?;
+            let end_index = match self.filter.end_index {
+                Some(last_index) => (begin_index + MATCHLIST_PAGE_SIZE).min(last_index),
+                None => begin_index + MATCHLIST_PAGE_SIZE,
+            };
+            if begin_index >= end_index {
+                self.next_index = None;
+                return None;
+            }
+
+            let page_filter = MatchlistFilter {
+                begin_index : Some(begin_index),
+                end_index : Some(end_index),
+                ..self.filter.clone()
+            };
+            let (inner, region, account_id) = (self.inner.clone(), self.region, self.encrypted_account_id.clone());
+            let matchlist = Context::query_with_retry(&self.inner.retry_policy,
+                move || {
+                    Context::_try_query_match_v4_matchlist_by_account(inner.clone(), region, account_id.clone(), page_filter.clone())
+                }).await;
+
+            match matchlist {
+                Ok(matchlist) => {
+                    // the api answers with the range it actually served, which stops short at the end of the history
+                    let exhausted = matchlist.matches.is_empty()
+                                    || matchlist.end_index >= matchlist.total_games
+                                    || matchlist.end_index <= begin_index;
+                    self.next_index = if exhausted { None } else { Some(matchlist.end_index) };
+                    self.page.extend(matchlist.matches);
+                },
+                Err(e) => {
+                    self.next_index = None;
+                    return Some(Err(e));
+                },
+            }
+        }
+    }
+}
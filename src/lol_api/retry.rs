@@ -0,0 +1,172 @@
+//! Decides which failed queries are worth sending again
+//! and how long to wait before doing so. A context holds a
+//! single `RetryPolicy` which every `query_*` method follows,
+//! while the `try_query_*` methods never retry.
+
+// external uses
+use rand::Rng;
+use tokio::time::Duration;
+
+// my mods
+use super::Error;
+
+/// The kinds of failure a `RetryPolicy` has a rule for.
+/// Anything else (e.g. a 404) is never retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    RateLimited,    // a 429, or an endpoint still on cooldown
+    ServerError,    // a 500, 502, 503 or 504 response
+    Timeout,        // no response in time
+    Connection,     // couldn't reach the server at all
+}
+
+/// How the `query_*` methods of a context retry failed queries.
+///
+/// Rate limited queries wait for as long as the server (or the
+/// cooldown) says, while the others back off exponentially from
+/// `initial_backoff`, with a random part of each wait taken off
+/// so queries which failed together don't all retry together.
+///
+/// # Example
+///
+/// ```ignore
+/// let ctx = Context::builder(&key)
+///     .retry_policy(RetryPolicy { max_retries : 5, retry_timeouts : false, ..RetryPolicy::default() })
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries : usize,                // how many times a query is sent again before giving up
+    pub initial_backoff : Duration,         // wait before the first retry of an error without a retry time
+    pub max_backoff : Duration,             // the backoff never grows past this
+    pub multiplier : f64,                   // how much the backoff grows with each retry
+    pub jitter : f64,                       // the largest fraction of each backoff randomly taken off, in [0, 1]
+    pub max_elapsed : Option<Duration>,     // give up rather than retry past this long since the first attempt
+    pub retry_rate_limited : bool,
+    pub retry_server_errors : bool,
+    pub retry_timeouts : bool,
+    pub retry_connection_errors : bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries : 3,
+            initial_backoff : Duration::from_millis(500),
+            max_backoff : Duration::from_secs(30),
+            multiplier : 2.0,
+            jitter : 0.5,
+            max_elapsed : Some(Duration::from_secs(300)),
+            retry_rate_limited : true,
+            retry_server_errors : true,
+            retry_timeouts : true,
+            retry_connection_errors : true,
+        }
+    }
+}
+
+impl RetryPolicy {
+
+    /// ctor - creates a policy which never retries
+    pub fn none() -> RetryPolicy {
+        RetryPolicy { max_retries : 0, ..RetryPolicy::default() }
+    }
+
+    /// Checks whether the policy retries the given class of error at all
+    pub fn retries(&self, class : ErrorClass) -> bool {
+        match class {
+            ErrorClass::RateLimited => self.retry_rate_limited,
+            ErrorClass::ServerError => self.retry_server_errors,
+            ErrorClass::Timeout => self.retry_timeouts,
+            ErrorClass::Connection => self.retry_connection_errors,
+        }
+    }
+
+    /// Gets the backoff before the given retry, before jitter is applied
+    ///
+    /// # Arguments
+    ///
+    /// `retry` - how many retries came before this one
+    pub fn backoff(&self, retry : usize) -> Duration {
+        let exponent = retry.min(i32::MAX as usize) as i32;
+        let secs = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        Duration::from_secs_f64(secs.min(self.max_backoff.as_secs_f64()))
+    }
+
+    /// Decides whether a failed query should be sent again
+    ///
+    /// # Arguments
+    ///
+    /// `error` - the error the last attempt failed with
+    /// `retry` - how many retries came before this one
+    /// `elapsed` - the time since the first attempt was sent
+    ///
+    /// # Return
+    ///
+    /// How long to wait before retrying, or `None` to give up
+    pub fn retry_delay(&self, error : &Error, retry : usize, elapsed : Duration) -> Option<Duration> {
+
+        if retry >= self.max_retries {
+            return None;
+        }
+        let class = error.class()?;
+        if !self.retries(class) {
+            return None;
+        }
+
+        let delay = match error.retry_time() {
+            Some(retry_time) => retry_time,
+            None => {
+                let jitter = self.jitter.clamp(0.0, 1.0) * rand::thread_rng().gen::<f64>();
+                self.backoff(retry).mul_f64(1.0 - jitter)
+            },
+        };
+        match self.max_elapsed {
+            Some(max_elapsed) if elapsed + delay > max_elapsed => None,
+            _ => Some(delay),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{RetryPolicy, Duration};
+    use crate::lol_api::{Error, ErrorKind, RateLimitType};
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let policy = RetryPolicy {
+            initial_backoff : Duration::from_millis(100),
+            max_backoff : Duration::from_millis(500),
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff(3), Duration::from_millis(500));
+        assert_eq!(policy.backoff(1000), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_retry_delay_rules() {
+        let policy = RetryPolicy {
+            initial_backoff : Duration::from_millis(100),
+            retry_timeouts : false,
+            ..RetryPolicy::default()
+        };
+        let server_error = Error::from(ErrorKind::ResponseStatus(StatusCode::SERVICE_UNAVAILABLE));
+        let delay = policy.retry_delay(&server_error, 0, Duration::from_secs(0)).unwrap();
+        assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(100));
+        assert!(policy.retry_delay(&server_error, 3, Duration::from_secs(0)).is_none());
+
+        // rate limits wait exactly as long as the server says, unless that's past the max elapsed time
+        let rate_limited = Error::from(ErrorKind::RateLimited(Some(RateLimitType::Method), Duration::from_secs(2)));
+        assert_eq!(policy.retry_delay(&rate_limited, 0, Duration::from_secs(0)), Some(Duration::from_secs(2)));
+        assert!(policy.retry_delay(&rate_limited, 0, Duration::from_secs(299)).is_none());
+
+        assert!(policy.retry_delay(&Error::from(ErrorKind::Timeout("uri".to_string())), 0, Duration::from_secs(0)).is_none());
+        assert!(policy.retry_delay(&Error::from(ErrorKind::ResponseStatus(StatusCode::NOT_FOUND)), 0, Duration::from_secs(0)).is_none());
+        assert!(RetryPolicy::none().retry_delay(&server_error, 0, Duration::from_secs(0)).is_none());
+    }
+}
@@ -0,0 +1,37 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct AccountDto {
+    pub puuid : String,         // encrypted puuid
+    pub game_name : String,     // the part of the riot id before the '#'
+    pub tag_line : String,      // the part of the riot id after the '#'
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Method {
+    ByRiotId = 0,
+    ByPuuid,
+}
+
+/// Splits a riot id (e.g. `"hi#NA1"`) into its game name and tag line
+///
+/// # Return
+///
+/// The `(game_name, tag_line)` pair, or `None` if the string
+/// isn't a riot id (e.g. it's a plain summoner name)
+pub fn split_riot_id(riot_id : &str) -> Option<(&str, &str)> {
+    let (game_name, tag_line) = riot_id.rsplit_once('#')?;
+    if game_name.is_empty() || tag_line.is_empty() {
+        return None;
+    }
+    Some((game_name, tag_line))
+}
+
+pub fn by_riot_id_uri(game_name : &str, tag_line : &str) -> String {
+    format!("/riot/account/v1/accounts/by-riot-id/{}/{}", game_name, tag_line)
+}
+
+pub fn by_puuid_uri(puuid : &str) -> String {
+    format!("/riot/account/v1/accounts/by-puuid/{}", puuid)
+}
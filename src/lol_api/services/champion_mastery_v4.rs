@@ -0,0 +1,34 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct ChampionMasteryDto {
+    pub champion_points_until_next_level : i64,    // 0 once the champion is at max level
+    pub chest_granted : bool,                       // whether a chest was granted this season
+    pub champion_id : i64,
+    pub last_play_time : i64,                       // epoch millis
+    pub champion_level : i32,
+    pub summoner_id : String,                       // encrypted summoner id
+    pub champion_points : i64,
+    pub champion_points_since_last_level : i64,
+    pub tokens_earned : i32,                        // tokens towards the next mastery level
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Method {
+    BySummoner = 0,
+    BySummonerAndChampion,
+    ScoreBySummoner,
+}
+
+pub fn by_summoner_uri(encrypted_summoner_id : &str) -> String {
+    format!("/lol/champion-mastery/v4/champion-masteries/by-summoner/{}", encrypted_summoner_id)
+}
+
+pub fn by_summoner_and_champion_uri(encrypted_summoner_id : &str, champion_id : i64) -> String {
+    format!("/lol/champion-mastery/v4/champion-masteries/by-summoner/{}/by-champion/{}", encrypted_summoner_id, champion_id)
+}
+
+pub fn score_by_summoner_uri(encrypted_summoner_id : &str) -> String {
+    format!("/lol/champion-mastery/v4/scores/by-summoner/{}", encrypted_summoner_id)
+}
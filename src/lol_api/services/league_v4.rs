@@ -0,0 +1,121 @@
+use serde::Deserialize;
+
+/// The ranked queues which have a ladder. Converts to (and
+/// parses from) the api's name for the queue, e.g. `"RANKED_SOLO_5x5"`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, EnumIter, EnumString, IntoStaticStr)]
+pub enum Queue {
+    #[strum(serialize = "RANKED_SOLO_5x5")]
+    RankedSolo5x5,
+    #[strum(serialize = "RANKED_FLEX_SR")]
+    RankedFlexSr,
+    #[strum(serialize = "RANKED_FLEX_TT")]
+    RankedFlexTt,
+}
+
+/// The tiers which are split into divisions, and so can be
+/// listed with the `entries` method. The apex tiers (master and up)
+/// each have a single league instead. Converts to (and parses
+/// from) the api's name for the tier, e.g. `"GOLD"`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, EnumIter, EnumString, IntoStaticStr)]
+#[strum(serialize_all = "UPPERCASE")]
+pub enum Tier {
+    Iron,
+    Bronze,
+    Silver,
+    Gold,
+    Platinum,
+    Diamond,
+}
+
+/// A division of a tier, from lowest (IV) to highest (I)
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, EnumIter, EnumString, IntoStaticStr)]
+pub enum Division {
+    IV,
+    III,
+    II,
+    I,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct LeagueListDto {
+    pub league_id : String,
+    pub entries : Vec<LeagueItemDto>,
+    pub tier : String,
+    pub name : String,
+    pub queue : String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct LeagueItemDto {
+    pub fresh_blood : bool,
+    pub wins : i32,
+    pub summoner_name : String,
+    pub mini_series : Option<MiniSeriesDto>,    // only while in promos
+    pub inactive : bool,
+    pub veteran : bool,
+    pub hot_streak : bool,
+    pub rank : String,
+    pub league_points : i32,
+    pub losses : i32,
+    pub summoner_id : String,                   // encrypted summoner id
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct LeagueEntryDto {
+    pub league_id : String,
+    pub summoner_id : String,                   // encrypted summoner id
+    pub summoner_name : String,
+    pub queue_type : String,
+    pub tier : String,
+    pub rank : String,                          // the division, e.g. "II"
+    pub league_points : i32,
+    pub wins : i32,
+    pub losses : i32,
+    pub hot_streak : bool,
+    pub veteran : bool,
+    pub fresh_blood : bool,
+    pub inactive : bool,
+    pub mini_series : Option<MiniSeriesDto>,    // only while in promos
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct MiniSeriesDto {
+    pub losses : i32,
+    pub progress : String,                      // e.g. "WLN" for win, loss, not played
+    pub target : i32,
+    pub wins : i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Method {
+    ChallengerLeague = 0,
+    GrandmasterLeague,
+    MasterLeague,
+    Entries,
+    EntriesBySummoner,
+}
+
+pub fn challenger_league_uri(queue : Queue) -> String {
+    format!("/lol/league/v4/challengerleagues/by-queue/{}", <&str>::from(queue))
+}
+
+pub fn grandmaster_league_uri(queue : Queue) -> String {
+    format!("/lol/league/v4/grandmasterleagues/by-queue/{}", <&str>::from(queue))
+}
+
+pub fn master_league_uri(queue : Queue) -> String {
+    format!("/lol/league/v4/masterleagues/by-queue/{}", <&str>::from(queue))
+}
+
+pub fn entries_uri(queue : Queue, tier : Tier, division : Division, page : i32) -> String {
+    format!("/lol/league/v4/entries/{}/{}/{}?page={}",
+        <&str>::from(queue), <&str>::from(tier), <&str>::from(division), page)
+}
+
+pub fn entries_by_summoner_uri(encrypted_summoner_id : &str) -> String {
+    format!("/lol/league/v4/entries/by-summoner/{}", encrypted_summoner_id)
+}
@@ -23,6 +23,38 @@ pub struct MatchReferenceDto {
     pub timestamp : i64,
 }
 
+/// Filters and pagination for a matchlist query. Every
+/// field is optional, and the defaults leave the query
+/// unfiltered (the most recent page of every queue).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MatchlistFilter {
+    pub queue : Vec<i32>,               // queue ids to keep (e.g. 420 for ranked solo), empty for all
+    pub season : Vec<i32>,              // season ids to keep, empty for all
+    pub champion : Vec<i32>,            // champion ids to keep, empty for all
+    pub begin_time : Option<i64>,       // epoch millis
+    pub end_time : Option<i64>,         // epoch millis
+    pub begin_index : Option<i32>,
+    pub end_index : Option<i32>,        // the api serves at most 100 matches per page
+}
+
+impl MatchlistFilter {
+
+    /// Gets the query string for the filter, including the
+    /// leading `?`, or an empty string if nothing is filtered
+    pub fn query_string(&self) -> String {
+        let mut params : Vec<String> = Vec::new();
+        params.extend(self.queue.iter().map(|queue| format!("queue={}", queue)));
+        params.extend(self.season.iter().map(|season| format!("season={}", season)));
+        params.extend(self.champion.iter().map(|champion| format!("champion={}", champion)));
+        params.extend(self.begin_time.map(|time| format!("beginTime={}", time)));
+        params.extend(self.end_time.map(|time| format!("endTime={}", time)));
+        params.extend(self.begin_index.map(|index| format!("beginIndex={}", index)));
+        params.extend(self.end_index.map(|index| format!("endIndex={}", index)));
+
+        if params.is_empty() { String::new() } else { format!("?{}", params.join("&")) }
+    }
+}
+
 #[derive(Deserialize, Debug, Default)]
 #[serde(rename_all="camelCase", default)]
 pub struct MatchDto {
@@ -246,16 +278,251 @@ pub struct MasteryDto {
     pub mastery_id : i32,
 }
 
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct MatchTimelineDto {
+    pub frames : Vec<MatchFrameDto>,
+    pub frame_interval : i64,                   // ms between frames, usually 60000
+}
+
+impl MatchTimelineDto {
+
+    /// Gets the first frame taken at or after the given minute
+    /// of the game
+    /// 
+    /// # Arguments
+    /// 
+    /// `minute` - the game time in minutes
+    /// 
+    /// # Return
+    /// 
+    /// The frame, or `None` if the game ended before `minute`
+    pub fn frame_at(&self, minute : i64) -> Option<&MatchFrameDto> {
+        self.frames.iter().find(|frame| frame.timestamp >= minute * 60_000)
+    }
+
+    /// Gets a participant's frame at the given minute of the game
+    /// 
+    /// # Arguments
+    /// 
+    /// `participant_id` - the participant's id within the match (1 to 10)
+    /// `minute` - the game time in minutes
+    pub fn participant_frame_at(&self, participant_id : i32, minute : i64) -> Option<&MatchParticipantFrameDto> {
+        self.frame_at(minute)?.participant_frames.get(&participant_id.to_string())
+    }
+
+    /// Gets the total gold a participant had earned by the given minute
+    pub fn gold_at(&self, participant_id : i32, minute : i64) -> Option<i32> {
+        self.participant_frame_at(participant_id, minute).map(|frame| frame.total_gold)
+    }
+
+    /// Gets the experience a participant had by the given minute
+    pub fn xp_at(&self, participant_id : i32, minute : i64) -> Option<i32> {
+        self.participant_frame_at(participant_id, minute).map(|frame| frame.xp)
+    }
+
+    /// Iterates over every event of the game in the order they happened
+    pub fn events(&self) -> impl Iterator<Item = &MatchEventDto> {
+        self.frames.iter().flat_map(|frame| frame.events.iter())
+    }
+
+    /// Iterates over the objectives taken during the game (buildings
+    /// and elite monsters, e.g. towers and dragons) in the order they happened
+    pub fn objective_events(&self) -> impl Iterator<Item = &MatchEventDto> {
+        self.events().filter(|event| matches!(event, MatchEventDto::BuildingKill(_) | MatchEventDto::EliteMonsterKill(_)))
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct MatchFrameDto {
+    pub participant_frames : HashMap<String, MatchParticipantFrameDto>,    // keyed by participant id
+    pub events : Vec<MatchEventDto>,
+    pub timestamp : i64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct MatchParticipantFrameDto {
+    pub participant_id : i32,
+    pub minions_killed : i32,
+    pub team_score : i32,
+    pub dominion_score : i32,
+    pub total_gold : i32,
+    pub level : i32,
+    pub xp : i32,
+    pub current_gold : i32,
+    pub position : Option<MatchPositionDto>,
+    pub jungle_minions_killed : i32,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all="camelCase", default)]
+pub struct MatchPositionDto {
+    pub x : i32,
+    pub y : i32,
+}
+
+/// A single timeline event. The api tells the events apart
+/// with their `type` field, and each type only fills in some
+/// of the event fields, so each one gets its own struct.
+#[derive(Deserialize, Debug)]
+#[serde(tag="type", rename_all="SCREAMING_SNAKE_CASE")]
+pub enum MatchEventDto {
+    ChampionKill(ChampionKillEventDto),
+    WardPlaced(WardPlacedEventDto),
+    WardKill(WardKillEventDto),
+    BuildingKill(BuildingKillEventDto),
+    EliteMonsterKill(EliteMonsterKillEventDto),
+    ItemPurchased(ItemEventDto),
+    ItemSold(ItemEventDto),
+    ItemDestroyed(ItemEventDto),
+    ItemUndo(ItemUndoEventDto),
+    SkillLevelUp(SkillLevelUpEventDto),
+    AscendedEvent(AscendedEventDto),
+    CapturePoint(CapturePointEventDto),
+    PoroKingSummon(PoroKingSummonEventDto),
+    #[serde(other)]
+    Unknown,                                    // event types added to the api after this was written
+}
+
+impl MatchEventDto {
+
+    /// Gets the game time in ms the event happened at, if known
+    pub fn timestamp(&self) -> Option<i64> {
+        match self {
+            MatchEventDto::ChampionKill(event) => Some(event.timestamp),
+            MatchEventDto::WardPlaced(event) => Some(event.timestamp),
+            MatchEventDto::WardKill(event) => Some(event.timestamp),
+            MatchEventDto::BuildingKill(event) => Some(event.timestamp),
+            MatchEventDto::EliteMonsterKill(event) => Some(event.timestamp),
+            MatchEventDto::ItemPurchased(event)
+            | MatchEventDto::ItemSold(event)
+            | MatchEventDto::ItemDestroyed(event) => Some(event.timestamp),
+            MatchEventDto::ItemUndo(event) => Some(event.timestamp),
+            MatchEventDto::SkillLevelUp(event) => Some(event.timestamp),
+            MatchEventDto::AscendedEvent(event) => Some(event.timestamp),
+            MatchEventDto::CapturePoint(event) => Some(event.timestamp),
+            MatchEventDto::PoroKingSummon(event) => Some(event.timestamp),
+            MatchEventDto::Unknown => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct ChampionKillEventDto {
+    pub timestamp : i64,
+    pub position : MatchPositionDto,
+    pub killer_id : i32,                        // 0 for executions by towers/minions
+    pub victim_id : i32,
+    pub assisting_participant_ids : Vec<i32>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct WardPlacedEventDto {
+    pub timestamp : i64,
+    pub creator_id : i32,
+    pub ward_type : String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct WardKillEventDto {
+    pub timestamp : i64,
+    pub killer_id : i32,
+    pub ward_type : String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct BuildingKillEventDto {
+    pub timestamp : i64,
+    pub position : MatchPositionDto,
+    pub killer_id : i32,
+    pub assisting_participant_ids : Vec<i32>,
+    pub team_id : i32,                          // the team which owned the building
+    pub building_type : String,                 // TOWER_BUILDING or INHIBITOR_BUILDING
+    pub lane_type : String,
+    pub tower_type : String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct EliteMonsterKillEventDto {
+    pub timestamp : i64,
+    pub position : MatchPositionDto,
+    pub killer_id : i32,
+    pub monster_type : String,                  // e.g. DRAGON, BARON_NASHOR, RIFTHERALD
+    pub monster_sub_type : String,              // e.g. FIRE_DRAGON
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct ItemEventDto {
+    pub timestamp : i64,
+    pub participant_id : i32,
+    pub item_id : i32,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct ItemUndoEventDto {
+    pub timestamp : i64,
+    pub participant_id : i32,
+    pub before_id : i32,
+    pub after_id : i32,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct SkillLevelUpEventDto {
+    pub timestamp : i64,
+    pub participant_id : i32,
+    pub skill_slot : i32,
+    pub level_up_type : String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct AscendedEventDto {
+    pub timestamp : i64,
+    pub position : MatchPositionDto,
+    pub killer_id : i32,
+    pub ascended_type : String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct CapturePointEventDto {
+    pub timestamp : i64,
+    pub position : MatchPositionDto,
+    pub killer_id : i32,
+    pub point_captured : String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct PoroKingSummonEventDto {
+    pub timestamp : i64,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Method {
     MatchlistByAccount = 0,
     MatchById,
+    TimelineByMatchId,
 }
 
-pub fn matchlist_by_account_uri(encrypted_account_id : &str) -> String {
-    format!("/lol/match/v4/matchlists/by-account/{}", encrypted_account_id)
+pub fn matchlist_by_account_uri(encrypted_account_id : &str, filter : &MatchlistFilter) -> String {
+    format!("/lol/match/v4/matchlists/by-account/{}{}", encrypted_account_id, filter.query_string())
 }
 
 pub fn match_by_id_uri(match_id : i64) -> String {
     format!("/lol/match/v4/matches/{}", match_id)
-}
\ No newline at end of file
+}
+
+pub fn timeline_by_match_id_uri(match_id : i64) -> String {
+    format!("/lol/match/v4/timelines/by-match/{}", match_id)
+}
@@ -0,0 +1,226 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct MatchDto {
+    pub metadata : MetadataDto,
+    pub info : InfoDto,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct MetadataDto {
+    pub data_version : String,
+    pub match_id : String,              // e.g. NA1_1234
+    pub participants : Vec<String>,     // participant puuids
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct InfoDto {
+    pub game_creation : i64,
+    pub game_duration : i64,
+    pub game_end_timestamp : i64,
+    pub game_id : i64,
+    pub game_mode : String,
+    pub game_name : String,
+    pub game_start_timestamp : i64,
+    pub game_type : String,
+    pub game_version : String,
+    pub map_id : i32,
+    pub participants : Vec<ParticipantDto>,
+    pub platform_id : String,
+    pub queue_id : i32,
+    pub teams : Vec<TeamDto>,
+    pub tournament_code : String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct ParticipantDto {
+    pub assists : i32,
+    pub baron_kills : i32,
+    pub bounty_level : i32,
+    pub champ_experience : i32,
+    pub champ_level : i32,
+    pub champion_id : i32,
+    pub champion_name : String,
+    pub champion_transform : i32,
+    pub consumables_purchased : i32,
+    pub damage_dealt_to_buildings : i64,
+    pub damage_dealt_to_objectives : i64,
+    pub damage_dealt_to_turrets : i64,
+    pub damage_self_mitigated : i64,
+    pub deaths : i32,
+    pub detector_wards_placed : i32,
+    pub double_kills : i32,
+    pub dragon_kills : i32,
+    pub first_blood_assist : bool,
+    pub first_blood_kill : bool,
+    pub first_tower_assist : bool,
+    pub first_tower_kill : bool,
+    pub game_ended_in_early_surrender : bool,
+    pub game_ended_in_surrender : bool,
+    pub gold_earned : i32,
+    pub gold_spent : i32,
+    pub individual_position : String,
+    pub inhibitor_kills : i32,
+    pub inhibitor_takedowns : i32,
+    pub inhibitors_lost : i32,
+    pub item0 : i32,
+    pub item1 : i32,
+    pub item2 : i32,
+    pub item3 : i32,
+    pub item4 : i32,
+    pub item5 : i32,
+    pub item6 : i32,
+    pub items_purchased : i32,
+    pub killing_sprees : i32,
+    pub kills : i32,
+    pub lane : String,
+    pub largest_critical_strike : i32,
+    pub largest_killing_spree : i32,
+    pub largest_multi_kill : i32,
+    pub longest_time_spent_living : i32,
+    pub magic_damage_dealt : i64,
+    pub magic_damage_dealt_to_champions : i64,
+    pub magic_damage_taken : i64,
+    pub neutral_minions_killed : i32,
+    pub nexus_kills : i32,
+    pub nexus_takedowns : i32,
+    pub nexus_lost : i32,
+    pub objectives_stolen : i32,
+    pub objectives_stolen_assists : i32,
+    pub participant_id : i32,
+    pub penta_kills : i32,
+    pub perks : PerksDto,
+    pub physical_damage_dealt : i64,
+    pub physical_damage_dealt_to_champions : i64,
+    pub physical_damage_taken : i64,
+    pub profile_icon : i32,
+    pub puuid : String,
+    pub quadra_kills : i32,
+    pub riot_id_game_name : String,
+    pub riot_id_tagline : String,
+    pub role : String,
+    pub sight_wards_bought_in_game : i32,
+    pub spell1_casts : i32,
+    pub spell2_casts : i32,
+    pub spell3_casts : i32,
+    pub spell4_casts : i32,
+    pub summoner1_casts : i32,
+    pub summoner1_id : i32,
+    pub summoner2_casts : i32,
+    pub summoner2_id : i32,
+    pub summoner_id : String,
+    pub summoner_level : i32,
+    pub summoner_name : String,
+    pub team_early_surrendered : bool,
+    pub team_id : i32,
+    pub team_position : String,
+    pub time_c_cing_others : i32,
+    pub time_played : i32,
+    pub total_damage_dealt : i64,
+    pub total_damage_dealt_to_champions : i64,
+    pub total_damage_shielded_on_teammates : i64,
+    pub total_damage_taken : i64,
+    pub total_heal : i64,
+    pub total_heals_on_teammates : i64,
+    pub total_minions_killed : i32,
+    pub total_time_c_c_dealt : i32,
+    pub total_time_spent_dead : i32,
+    pub total_units_healed : i32,
+    pub triple_kills : i32,
+    pub true_damage_dealt : i64,
+    pub true_damage_dealt_to_champions : i64,
+    pub true_damage_taken : i64,
+    pub turret_kills : i32,
+    pub turret_takedowns : i32,
+    pub turrets_lost : i32,
+    pub unreal_kills : i32,
+    pub vision_score : i32,
+    pub vision_wards_bought_in_game : i32,
+    pub wards_killed : i32,
+    pub wards_placed : i32,
+    pub win : bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct PerksDto {
+    pub stat_perks : PerkStatsDto,
+    pub styles : Vec<PerkStyleDto>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct PerkStatsDto {
+    pub defense : i32,
+    pub flex : i32,
+    pub offense : i32,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct PerkStyleDto {
+    pub description : String,           // primaryStyle or subStyle
+    pub selections : Vec<PerkStyleSelectionDto>,
+    pub style : i32,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct PerkStyleSelectionDto {
+    pub perk : i32,
+    pub var1 : i32,
+    pub var2 : i32,
+    pub var3 : i32,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct TeamDto {
+    pub bans : Vec<BanDto>,
+    pub objectives : ObjectivesDto,
+    pub team_id : i32,
+    pub win : bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct BanDto {
+    pub champion_id : i32,
+    pub pick_turn : i32,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct ObjectivesDto {
+    pub baron : ObjectiveDto,
+    pub champion : ObjectiveDto,
+    pub dragon : ObjectiveDto,
+    pub inhibitor : ObjectiveDto,
+    pub rift_herald : ObjectiveDto,
+    pub tower : ObjectiveDto,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct ObjectiveDto {
+    pub first : bool,
+    pub kills : i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Method {
+    MatchIdsByPuuid = 0,
+    MatchById,
+}
+
+pub fn match_ids_by_puuid_uri(puuid : &str) -> String {
+    format!("/lol/match/v5/matches/by-puuid/{}/ids", puuid)
+}
+
+pub fn match_by_id_uri(match_id : &str) -> String {
+    format!("/lol/match/v5/matches/{}", match_id)
+}
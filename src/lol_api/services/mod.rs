@@ -1,4 +1,9 @@
 
 // public uses
 pub mod summoner_v4;
-pub mod match_v4;
\ No newline at end of file
+pub mod match_v4;
+pub mod match_v5;
+pub mod league_v4;
+pub mod spectator_v4;
+pub mod champion_mastery_v4;
+pub mod account_v1;
@@ -0,0 +1,110 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct CurrentGameInfoDto {
+    pub game_id : i64,
+    pub game_type : String,
+    pub game_start_time : i64,                  // epoch millis
+    pub map_id : i64,
+    pub game_length : i64,                      // seconds since the game started
+    pub platform_id : String,
+    pub game_mode : String,
+    pub banned_champions : Vec<BannedChampionDto>,
+    pub game_queue_config_id : i64,
+    pub observers : ObserverDto,
+    pub participants : Vec<CurrentGameParticipantDto>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct BannedChampionDto {
+    pub pick_turn : i32,
+    pub champion_id : i64,
+    pub team_id : i64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct ObserverDto {
+    pub encryption_key : String,                // key to decrypt the spectator grid game data
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct CurrentGameParticipantDto {
+    pub champion_id : i64,
+    pub perks : PerksDto,
+    pub profile_icon_id : i64,
+    pub bot : bool,
+    pub team_id : i64,
+    pub summoner_name : String,
+    pub summoner_id : String,                   // encrypted summoner id
+    pub spell1_id : i64,
+    pub spell2_id : i64,
+    pub game_customization_objects : Vec<GameCustomizationObjectDto>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct PerksDto {
+    pub perk_ids : Vec<i64>,
+    pub perk_style : i64,
+    pub perk_sub_style : i64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct GameCustomizationObjectDto {
+    pub category : String,
+    pub content : String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct FeaturedGamesDto {
+    pub game_list : Vec<FeaturedGameInfoDto>,
+    pub client_refresh_interval : i64,          // suggested seconds to wait before refreshing
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct FeaturedGameInfoDto {
+    pub game_mode : String,
+    pub game_length : i64,
+    pub map_id : i64,
+    pub game_type : String,
+    pub banned_champions : Vec<BannedChampionDto>,
+    pub game_id : i64,
+    pub observers : ObserverDto,
+    pub game_queue_config_id : i64,
+    pub game_start_time : i64,
+    pub participants : Vec<ParticipantDto>,
+    pub platform_id : String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all="camelCase", default)]
+pub struct ParticipantDto {
+    pub bot : bool,
+    pub spell2_id : i64,
+    pub profile_icon_id : i64,
+    pub summoner_name : String,
+    pub champion_id : i64,
+    pub team_id : i64,
+    pub spell1_id : i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Method {
+    ActiveGameBySummoner = 0,
+    FeaturedGames,
+}
+
+pub fn active_game_by_summoner_uri(encrypted_summoner_id : &str) -> String {
+    format!("/lol/spectator/v4/active-games/by-summoner/{}", encrypted_summoner_id)
+}
+
+pub fn featured_games_uri() -> String {
+    "/lol/spectator/v4/featured-games".to_string()
+}
@@ -13,9 +13,12 @@ pub struct SummonerDto {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(clippy::enum_variant_names)] // named after the riot api's lookups
 pub enum Method {
     ByAccount = 0,
     ByName,
+    ByPuuid,
+    BySummonerId,
 }
 
 pub fn by_account_uri(encrypted_account_id : &str)->String {
@@ -24,4 +27,12 @@ pub fn by_account_uri(encrypted_account_id : &str)->String {
 
 pub fn by_name_uri(summoner_name : &str)->String {
     format!("/lol/summoner/v4/summoners/by-name/{}", summoner_name)
+}
+
+pub fn by_puuid_uri(puuid : &str)->String {
+    format!("/lol/summoner/v4/summoners/by-puuid/{}", puuid)
+}
+
+pub fn by_summoner_id_uri(encrypted_summoner_id : &str)->String {
+    format!("/lol/summoner/v4/summoners/{}", encrypted_summoner_id)
 }
\ No newline at end of file
@@ -0,0 +1,212 @@
+//! Saves the rate-limit state of a context to a file, so a
+//! process restarted in the middle of a window picks up where
+//! the last one left off rather than finding out about the full
+//! buckets from 429s (and the cooldowns that come with them).
+//!
+//! The file holds a JSON snapshot of every endpoint of every api
+//! key, with wall-clock timestamps. Keys are identified by a
+//! fingerprint, so the file never holds the keys themselves.
+//! The file is stamped with `STATE_FORMAT_VERSION`, and a file
+//! of any other version is discarded (with a message) on load.
+
+// external uses
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// my mods
+use super::Result;
+use super::endpoint::{Endpoint, EndpointSnapshot, Id};
+
+/// The version of the file format, which goes up whenever
+/// the snapshots change in a way older files can't be read as
+pub const STATE_FORMAT_VERSION : u32 = 1;
+
+/// The saved state of a single api key
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedKey {
+    fingerprint : u64,
+    endpoints : Vec<(Id, EndpointSnapshot)>,
+}
+
+/// The saved state of every api key in the file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedState {
+    version : u32,
+    keys : Vec<SavedKey>,
+}
+
+impl Default for SavedState {
+    fn default() -> SavedState {
+        SavedState {
+            version : STATE_FORMAT_VERSION,
+            keys : Vec::new(),
+        }
+    }
+}
+
+
+/// Identifies an api key without giving it away. This is the 64 bit
+/// FNV-1a hash of the key, which (unlike the std hashers) never changes
+/// between builds, so saved state and the coordinator keep matching
+/// the key after the crawler is built with a different toolchain.
+pub fn fingerprint(api_key : &str) -> u64 {
+    const FNV_OFFSET_BASIS : u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME : u64 = 0x0000_0100_0000_01b3;
+    api_key.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME))
+}
+
+impl SavedState {
+
+    /// Restores the endpoints of an api key, which are empty if
+    /// the key wasn't saved. Windows and cooldowns which ended
+    /// since are aged out.
+    pub fn endpoints(&self, api_key : &str) -> HashMap<Id, Endpoint> {
+        let fingerprint = fingerprint(api_key);
+        self.keys.iter()
+            .filter(|key| key.fingerprint == fingerprint)
+            .flat_map(|key| key.endpoints.iter())
+            .map(|(id, snapshot)| (*id, Endpoint::from_snapshot(snapshot)))
+            .collect()
+    }
+
+    /// Saves the endpoints of an api key, replacing any saved before
+    pub fn set_endpoints(&mut self, api_key : &str, endpoints : &HashMap<Id, Endpoint>) {
+        let fingerprint = fingerprint(api_key);
+        self.keys.retain(|key| key.fingerprint != fingerprint);
+        self.keys.push(SavedKey {
+            fingerprint,
+            endpoints : endpoints.iter().map(|(id, endpoint)| (*id, endpoint.snapshot())).collect(),
+        });
+    }
+}
+
+/// The file a context's rate-limit state is saved to
+#[derive(Debug, Clone)]
+pub struct StateFile {
+    path : PathBuf,
+}
+
+impl StateFile {
+
+    /// ctor - creates a state file at the given path.
+    /// The file is created on the first save.
+    pub fn new<P : AsRef<Path>>(path : P) -> StateFile {
+        StateFile {
+            path : path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Loads the saved state, which is empty if the file doesn't
+    /// exist yet. A file which can't be read (e.g. it's from another
+    /// version) is discarded, which is logged to stderr.
+    pub fn load(&self) -> SavedState {
+        match self.try_load() {
+            Ok(state) => state,
+            Err(e) => {
+                eprintln!("discarding the rate-limit state in {}: {}", self.path.display(), e);
+                SavedState::default()
+            },
+        }
+    }
+
+    /// Loads the saved state, failing if the file can't be
+    /// read or isn't of the current format version
+    fn try_load(&self) -> Result<SavedState> {
+        let contents = match fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(SavedState::default()),
+            Err(e) => return Err(e.into()),
+        };
+        // the version is checked before the rest, which may not parse in another version
+        let saved : serde_json::Value = serde_json::from_slice(&contents)?;
+        match saved.get("version").and_then(|version| version.as_u64()) {
+            Some(version) if version == u64::from(STATE_FORMAT_VERSION) => Ok(serde_json::from_value(saved)?),
+            Some(version) => Err(format!("the file is format version {}, expected {}", version, STATE_FORMAT_VERSION).into()),
+            None => Err(format!("the file has no format version, expected {}", STATE_FORMAT_VERSION).into()),
+        }
+    }
+
+    /// Saves the state, replacing the file
+    ///
+    /// # Remarks
+    ///
+    /// This blocks rather than being async, since the state is saved
+    /// as the context is dropped. The state is written to a temporary
+    /// file which is then moved into place, so a process killed in the
+    /// middle of saving never leaves a partial file behind.
+    pub fn save(&self, state : &SavedState) -> Result<()> {
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let contents = serde_json::to_vec(state)?;
+        let tmp_path = self.path.with_extension(format!("{}.tmp", rand::random::<u64>()));
+        fs::write(&tmp_path, contents)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{StateFile, SavedState, fingerprint};
+    use crate::lol_api::endpoint::{Endpoint, Id, Region, Host};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_fingerprint_is_stable() {
+        // the published FNV-1a test vectors
+        assert_eq!(fingerprint(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fingerprint("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fingerprint("foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn test_save_then_load() {
+        let path = std::env::temp_dir().join(format!("lol-state-test-{}.json", rand::random::<u64>()));
+        let state_file = StateFile::new(&path);
+        assert!(state_file.load().endpoints("key-1").is_empty());
+
+        let id = Id::from_host(Host::Platform(Region::Na1));
+        let mut endpoint = Endpoint::with_limits(&[(1, 60)]);
+        endpoint.reserve();
+        let mut endpoints = HashMap::new();
+        endpoints.insert(id, endpoint);
+
+        let mut state = SavedState::default();
+        state.set_endpoints("key-1", &endpoints);
+        state_file.save(&state).unwrap();
+
+        // only the key which was saved gets its state back
+        let state = state_file.load();
+        assert!(state.endpoints("key-2").is_empty());
+        let mut restored = state.endpoints("key-1");
+        assert!(restored.get_mut(&id).unwrap().time_until_ready().is_some());
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("key-1"));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_other_versions_are_discarded() {
+        let path = std::env::temp_dir().join(format!("lol-state-test-{}.json", rand::random::<u64>()));
+        let state_file = StateFile::new(&path);
+        let mut endpoints = HashMap::new();
+        endpoints.insert(Id::from_host(Host::Platform(Region::Na1)), Endpoint::with_limits(&[(1, 60)]));
+        let mut state = SavedState::default();
+        state.set_endpoints("key-1", &endpoints);
+        state_file.save(&state).unwrap();
+        assert!(std::fs::read_to_string(&path).unwrap().contains("\"version\":1"));
+
+        // a file from before the format was versioned, one from a later version, and garbage
+        for contents in &[r#"{"keys":[]}"#, r#"{"version":2,"keys":{}}"#, "not json"] {
+            std::fs::write(&path, contents).unwrap();
+            assert!(state_file.try_load().is_err());
+            assert!(state_file.load().endpoints("key-1").is_empty());
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
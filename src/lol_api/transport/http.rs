@@ -0,0 +1,79 @@
+//! The default transport, which sends
+//! requests to the riot servers using `reqwest`.
+
+// external uses
+use reqwest::Client;
+use tokio::time::Duration;
+
+// my mods
+use super::{Transport, TransportFuture, RawResponse};
+use crate::lol_api::{Error, ErrorKind};
+
+/// How long a query may take before it fails with an `ErrorKind::Timeout`,
+/// unless the transport was made with `with_timeout`. Without one, a
+/// connection the server stops answering on hangs the query forever.
+pub const DEFAULT_REQUEST_TIMEOUT : Duration = Duration::from_secs(30);
+
+/// A thin wrapper around a `reqwest::Client`. The client
+/// keeps its own connection pool, so one of these should be
+/// shared by every query a context sends.
+#[derive(Debug)]
+pub struct HttpTransport {
+    client : Client,
+}
+
+impl Default for HttpTransport {
+    fn default() -> HttpTransport {
+        HttpTransport::new()
+    }
+}
+
+impl HttpTransport {
+
+    /// ctor - creates a transport which times out
+    /// after `DEFAULT_REQUEST_TIMEOUT`
+    pub fn new() -> HttpTransport {
+        HttpTransport::with_timeout(DEFAULT_REQUEST_TIMEOUT)
+    }
+
+    /// ctor - creates a transport which gives up on a query
+    /// (with an `ErrorKind::Timeout`) if the whole response
+    /// hasn't arrived within the given time
+    pub fn with_timeout(timeout : Duration) -> HttpTransport {
+        HttpTransport {
+            client : Client::builder().timeout(timeout).build().expect("Unable to build http client."),
+        }
+    }
+
+    /// Sorts out the request errors a `RetryPolicy` has rules for.
+    /// Sending fails without a timeout only if the connection does,
+    /// as long as the request itself was well formed.
+    fn classify(e : reqwest::Error, uri : &str, sending : bool) -> Error {
+        if e.is_timeout() {
+            Error::with_chain(e, ErrorKind::Timeout(uri.to_string()))
+        } else if sending && !e.is_builder() && !e.is_redirect() && !e.is_status() {
+            Error::with_chain(e, ErrorKind::Connection(uri.to_string()))
+        } else {
+            Error::from(e)
+        }
+    }
+}
+
+impl Transport for HttpTransport {
+
+    fn get<'a>(&'a self, uri : &'a str, api_key : &'a str) -> TransportFuture<'a> {
+        Box::pin(async move {
+            let response = self.client.get(uri)
+                .header("X-Riot-Token", api_key)
+                .send().await
+                .map_err(|e| Self::classify(e, uri, true))?;
+
+            let status = response.status();
+            let headers = response.headers().clone();
+            let body = response.bytes().await
+                .map_err(|e| Self::classify(e, uri, false))?
+                .to_vec();
+            Ok(RawResponse::new(status, headers, body))
+        })
+    }
+}
@@ -0,0 +1,211 @@
+//! An in-memory transport that serves canned
+//! responses. Useful for exercising the context
+//! (rate limiting, retries, etc.) and the crawler
+//! without a riot api key or network access.
+
+// external uses
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{StatusCode, Url};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tokio::time::Duration;
+
+// my mods
+use super::{Transport, TransportFuture, RawResponse};
+use crate::lol_api::{Error, ErrorKind};
+
+/// A way a `MockResponse` can fail to arrive at all
+#[derive(Debug, Clone, Copy)]
+enum MockFailure {
+    Timeout,
+    Connection,
+}
+
+/// A canned response for the `MockTransport` to hand back.
+/// By default it is a 200 OK with a `Date` header of the time it is served
+/// and rate-limit headers for a generous 100 requests per second
+/// bucket that has only seen a single request, so it never
+/// puts an endpoint on cooldown unless told to.
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    status : StatusCode,
+    headers : Vec<(String, String)>,
+    body : Vec<u8>,
+    failure : Option<MockFailure>,
+    delay : Option<Duration>,
+}
+
+impl MockResponse {
+
+    /// ctor - creates a 200 OK response with the given json body
+    pub fn json(body : &str) -> MockResponse {
+        MockResponse {
+            status : StatusCode::OK,
+            headers : vec![
+                ("X-App-Rate-Limit".to_string(), "100:1".to_string()),
+                ("X-App-Rate-Limit-Count".to_string(), "1:1".to_string()),
+                ("X-Method-Rate-Limit".to_string(), "100:1".to_string()),
+                ("X-Method-Rate-Limit-Count".to_string(), "1:1".to_string()),
+            ],
+            body : body.as_bytes().to_vec(),
+            failure : None,
+            delay : None,
+        }
+    }
+
+    /// ctor - creates a response with the given status and a riot style
+    /// error body
+    pub fn error(status : StatusCode) -> MockResponse {
+        let body = format!("{{\"status\":{{\"message\":\"{}\",\"status_code\":{}}}}}",
+                           status.canonical_reason().unwrap_or(""), status.as_u16());
+        MockResponse {
+            status,
+            headers : Vec::new(),
+            body : body.into_bytes(),
+            failure : None,
+            delay : None,
+        }
+    }
+
+    /// ctor - creates a "response" which never arrives, so the
+    /// query fails with an `ErrorKind::Timeout`
+    pub fn timeout() -> MockResponse {
+        MockResponse { failure : Some(MockFailure::Timeout), ..MockResponse::error(StatusCode::GATEWAY_TIMEOUT) }
+    }
+
+    /// ctor - creates a "response" for a server which can't be
+    /// reached, so the query fails with an `ErrorKind::Connection`
+    pub fn connection_error() -> MockResponse {
+        MockResponse { failure : Some(MockFailure::Connection), ..MockResponse::error(StatusCode::BAD_GATEWAY) }
+    }
+
+    /// Overrides the status code of the response
+    pub fn status(mut self, status : StatusCode) -> MockResponse {
+        self.status = status;
+        self
+    }
+
+    /// Holds the response back for the given time, e.g. to
+    /// keep several queries in flight at once
+    pub fn delay(mut self, delay : Duration) -> MockResponse {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Sets a header on the response, replacing any previous
+    /// value for the same header name
+    pub fn header(mut self, name : &str, value : &str) -> MockResponse {
+        self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Builds the response the context will see. The `Date`
+    /// header is filled in with the current time unless one was
+    /// given explicitly.
+    fn to_raw(&self) -> RawResponse {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            headers.insert(HeaderName::from_bytes(name.as_bytes()).expect("invalid mock header name"),
+                           HeaderValue::from_str(value).expect("invalid mock header value"));
+        }
+        if !headers.contains_key("Date") {
+            let date = chrono::Utc::now().to_rfc2822();
+            headers.insert("Date", HeaderValue::from_str(&date).unwrap());
+        }
+        RawResponse::new(self.status, headers, self.body.clone())
+    }
+}
+
+/// Serves `MockResponse`s keyed by the path and query of the request
+/// uri (e.g. `/lol/summoner/v4/summoners/by-name/hi`), so the same
+/// responses are served regardless of the region host.
+///
+/// Each path holds a queue of responses. Responses are popped
+/// off the front in order, except the last one, which is
+/// served for every request after that. Requests for a path
+/// with no responses get a 404 NOT FOUND.
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    responses : Mutex<HashMap<String, VecDeque<MockResponse>>>,
+    requests : Mutex<Vec<String>>,
+    api_keys : Mutex<Vec<String>>,      // the api key of every request, in the same order
+}
+
+impl MockTransport {
+
+    /// ctor - creates a transport with no responses
+    pub fn new() -> MockTransport {
+        MockTransport::default()
+    }
+
+    /// Queues up a response for the given path
+    ///
+    /// # Arguments
+    ///
+    /// `path` - the path and query of the request uri to respond to
+    /// `response` - the response to serve
+    pub fn push_response(&self, path : &str, response : MockResponse) {
+        self.responses.lock().unwrap()
+            .entry(path.to_string())
+            .or_default()
+            .push_back(response);
+    }
+
+    /// Gets the path and query of every request sent so far, in order
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Counts how many requests were sent with the given api key
+    pub fn api_key_count(&self, api_key : &str) -> usize {
+        self.api_keys.lock().unwrap().iter().filter(|k| *k == api_key).count()
+    }
+
+    /// Counts how many requests were sent for the given path
+    pub fn request_count(&self, path : &str) -> usize {
+        self.requests.lock().unwrap().iter().filter(|p| *p == path).count()
+    }
+
+    /// Strips the scheme and host off of a uri
+    fn path_of(uri : &str) -> String {
+        match Url::parse(uri) {
+            Ok(url) => match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            },
+            Err(_) => uri.to_string(),
+        }
+    }
+
+    /// Picks the next response for the given path
+    fn next_response(&self, path : &str) -> MockResponse {
+        let mut responses = self.responses.lock().unwrap();
+        match responses.get_mut(path) {
+            Some(queue) if queue.len() > 1 => queue.pop_front().unwrap(),
+            Some(queue) if queue.len() == 1 => queue.front().unwrap().clone(),
+            _ => MockResponse::error(StatusCode::NOT_FOUND),
+        }
+    }
+}
+
+impl Transport for MockTransport {
+
+    fn get<'a>(&'a self, uri : &'a str, api_key : &'a str) -> TransportFuture<'a> {
+        let path = Self::path_of(uri);
+        self.requests.lock().unwrap().push(path.clone());
+        self.api_keys.lock().unwrap().push(api_key.to_string());
+        let response = self.next_response(&path);
+        let result = match response.failure {
+            Some(MockFailure::Timeout) => Err(Error::from(ErrorKind::Timeout(uri.to_string()))),
+            Some(MockFailure::Connection) => Err(Error::from(ErrorKind::Connection(uri.to_string()))),
+            None => Ok(response.to_raw()),
+        };
+        Box::pin(async move {
+            if let Some(delay) = response.delay {
+                tokio::time::delay_for(delay).await;
+            }
+            result
+        })
+    }
+}
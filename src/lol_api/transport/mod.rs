@@ -0,0 +1,97 @@
+//! The transport layer is the only part of the `lol_api`
+//! module that actually talks to the network. The `Context`
+//! holds a `Transport` trait object instead of a raw http
+//! client, so the rate-limit bookkeeping, retries, and anything
+//! built on top of the context (e.g. the crawler) can be driven
+//! by canned responses instead of the live riot servers.
+//!
+//! Two implementations are provided:
+//! * `HttpTransport` - sends real requests using `reqwest`
+//! * `MockTransport` - serves canned responses from memory
+
+// external uses
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::pin::Pin;
+
+// my mods
+use crate::lol_api::Result;
+mod http;
+mod mock;
+pub use http::{HttpTransport, DEFAULT_REQUEST_TIMEOUT};
+pub use mock::{MockTransport, MockResponse};
+
+/// The boxed future returned by a `Transport`. We box it
+/// ourselves so the trait stays object safe without pulling
+/// in a crate for async traits.
+pub type TransportFuture<'a> = Pin<Box<dyn Future<Output = Result<RawResponse>> + Send + 'a>>;
+
+/// Something that can execute a GET request against the riot api
+/// and hand back the full response (status, headers and body).
+///
+/// # Remarks
+///
+/// Implementations should only return an error if no response
+/// was received at all (e.g. a connection failure). Any response
+/// from the server, including 4xx and 5xx responses, should be
+/// returned as an `Ok(RawResponse)` so the context can inspect the
+/// status and headers to update its rate-limit state.
+pub trait Transport : Send + Sync + std::fmt::Debug {
+
+    /// Sends a GET request to the given uri
+    ///
+    /// # Arguments
+    ///
+    /// `uri` - the full uri to execute the GET request against
+    /// `api_key` - the riot api key to send in the `X-Riot-Token` header
+    fn get<'a>(&'a self, uri : &'a str, api_key : &'a str) -> TransportFuture<'a>;
+}
+
+/// A fully received server response. Unlike `reqwest::Response`
+/// the body has already been read, so the response can be built
+/// by hand and inspected as many times as we like.
+#[derive(Debug, Clone)]
+pub struct RawResponse {
+    status : StatusCode,
+    headers : HeaderMap,
+    body : Vec<u8>,
+}
+
+impl RawResponse {
+
+    /// ctor - creates a response from its parts
+    pub fn new(status : StatusCode, headers : HeaderMap, body : Vec<u8>) -> RawResponse {
+        RawResponse {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    /// Gets the status code the server responded with
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Gets the response headers
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Gets the raw response body
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Deserializes the body as json into the requested type
+    ///
+    /// # Return
+    ///
+    /// The deserialized body or an error if the body was not valid
+    /// json for the type `T`
+    pub fn json<T : DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_slice::<T>(&self.body)?)
+    }
+}
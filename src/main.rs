@@ -2,26 +2,25 @@
 // extern crate definitions
 #[macro_use]
 extern crate error_chain;
-extern crate reqwest;
-extern crate strum;
-#[macro_use]
-extern crate strum_macros;
 extern crate tokio;
 
-// internal mods
-mod lol_api;
-mod crawler;
-mod util;
-
+use lol_match_crawler::{lol_api, crawler, util};
 use std::env;
 
+/// Where the rate-limit state is kept between runs
+const RATE_LIMIT_STATE_FILE : &str = "./rate_limits.json";
+
 fn usage(){
-    println!("Usage: lol-match-crawler.exe")
+    println!("Usage: lol-match-crawler.exe [region (default na1)] [rate-limit coordinator socket (default none)]")
 }
 
 error_chain!{
     links {
-        Crawler(crate::crawler::Error, crate::crawler::ErrorKind);
+        Crawler(crawler::Error, crawler::ErrorKind);
+    }
+
+    foreign_links {
+        ParseRegion(::strum::ParseError);
     }
 }
 
@@ -29,28 +28,55 @@ async fn do_main() -> Result<()> {
 
     // ensure proper number of args
     let args : Vec<String> = env::args().collect();
-    if args.len() != 1 {
+    if args.len() > 3 {
         usage();
-        return Err(Error::from(format!("Invalid number of command line arguments. Expected 0, got {}", args.len())));
+        return Err(Error::from(format!("Invalid number of command line arguments. Expected at most 2, got {}", args.len() - 1)));
     }
 
-    // get api key from key.txt
-    let key = util::get_key();
+    // region to crawl, e.g. "euw1"
+    let region : lol_api::Region = match args.get(1) {
+        Some(region) => region.to_lowercase().parse()
+                            .chain_err(|| format!("Unknown region {}", region))?,
+        None => lol_api::Region::Na1,
+    };
+
+    // coordinator shared with the other crawler processes on this machine, e.g. "/tmp/lol-rate-limit-coordinator.sock"
+    let coordinator = args.get(2);
+
+    // get api keys from key.txt, one per line
+    let keys = util::get_keys();
+    if keys.is_empty() {
+        return Err(Error::from("No api key found in key.txt"));
+    }
 
-    //instance ctx
-    let ctx = lol_api::Context::new(&key);
+    //instance ctx, remembering recent lookups since the crawlers revisit the same players
+    let ctx = keys[1..].iter()
+        .fold(lol_api::Context::builder(&keys[0]), |builder, key| builder.add_api_key(key))
+        .memory_cache(10_000)
+        .state_file(RATE_LIMIT_STATE_FILE);
+    let ctx = match coordinator {
+        Some(path) => ctx.coordinator(path),
+        None => ctx,
+    }.build();
 
-    // run the crawlers in a join
+    // run the crawlers in a join, until they finish or ctrl-c is hit
+    // (either way the ctx is dropped on return, which saves the rate-limit state)
     let c1 = crawler::Crawler::new(ctx).await.expect("unable to instance riot api crawler!");
     let c2 = c1.clone();
     let c3 = c1.clone();
     let c4 = c1.clone();
-    let r = tokio::join!(
-        c1.start_crawl("hi", 10),
-        c2.start_crawl("hi", 10),
-        c3.start_crawl("hi", 10),
-        c4.start_crawl("hi", 10),
-    );
+    let crawl = async {
+        tokio::join!(
+            c1.start_crawl(region, "hi", 10),
+            c2.start_crawl(region, "hi", 10),
+            c3.start_crawl(region, "hi", 10),
+            c4.start_crawl(region, "hi", 10),
+        )
+    };
+    let r = tokio::select! {
+        r = crawl => r,
+        _ = tokio::signal::ctrl_c() => return Ok(()),
+    };
 
     r.0?;
     r.1?;
@@ -0,0 +1,9 @@
+error_chain!{
+
+    foreign_links {
+        Io(::std::io::Error);
+        Hyper(::hyper::Error);
+        AddrParse(::std::net::AddrParseError);
+    }
+
+}
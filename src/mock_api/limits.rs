@@ -0,0 +1,127 @@
+//! Simulated rate-limit buckets for the mock server.
+//! These behave like the riot api's buckets: a window
+//! starts with the first request after the previous window
+//! ended, and once `limit` requests were counted in a window
+//! every further request is refused until it ends.
+
+// external uses
+use std::time::{Duration, Instant};
+
+/// A single simulated rate-limit bucket
+#[derive(Debug)]
+struct SimulatedBucket {
+    limit : u64,
+    window : Duration,
+    count : u64,
+    window_start : Instant,
+}
+
+impl SimulatedBucket {
+
+    /// Gets how long until the current window ends
+    fn time_left(&self, now : Instant) -> Duration {
+        self.window.checked_sub(now.duration_since(self.window_start)).unwrap_or_default()
+    }
+}
+
+/// The buckets for a single rate-limited thing
+/// (e.g. the application in one region, or one method)
+#[derive(Debug)]
+pub struct BucketSet {
+    buckets : Vec<SimulatedBucket>,
+}
+
+impl BucketSet {
+
+    /// ctor - creates empty buckets for the given limits
+    ///
+    /// # Arguments
+    ///
+    /// `limits` - pairs of (limit, window in seconds)
+    pub fn new(limits : &[(u64, u64)]) -> BucketSet {
+        let now = Instant::now();
+        BucketSet {
+            buckets : limits.iter().map(|&(limit, window)| SimulatedBucket {
+                limit,
+                window : Duration::from_secs(window),
+                count : 0,
+                window_start : now,
+            }).collect()
+        }
+    }
+
+    /// Resets the count of any bucket whose window has ended
+    fn roll_windows(&mut self, now : Instant) {
+        for bucket in self.buckets.iter_mut() {
+            if bucket.count > 0 && bucket.time_left(now) == Duration::from_secs(0) {
+                bucket.count = 0;
+            }
+        }
+    }
+
+    /// Checks if another request fits in the buckets
+    ///
+    /// # Return
+    ///
+    /// `None` if the request can go through, otherwise the
+    /// time until the last full bucket has room again
+    pub fn retry_after(&mut self, now : Instant) -> Option<Duration> {
+        self.roll_windows(now);
+        self.buckets.iter()
+            .filter(|bucket| bucket.count >= bucket.limit)
+            .map(|bucket| bucket.time_left(now))
+            .max()
+    }
+
+    /// Counts a request against every bucket
+    pub fn record(&mut self, now : Instant) {
+        self.roll_windows(now);
+        for bucket in self.buckets.iter_mut() {
+            if bucket.count == 0 {
+                bucket.window_start = now;
+            }
+            bucket.count += 1;
+        }
+    }
+
+    /// Formats the limits like the `X-*-Rate-Limit` headers (e.g. `20:1,100:120`)
+    pub fn limits_header(&self) -> String {
+        self.buckets.iter()
+            .map(|bucket| format!("{}:{}", bucket.limit, bucket.window.as_secs()))
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+
+    /// Formats the counts like the `X-*-Rate-Limit-Count` headers (e.g. `1:1,1:120`)
+    pub fn counts_header(&self) -> String {
+        self.buckets.iter()
+            .map(|bucket| format!("{}:{}", bucket.count, bucket.window.as_secs()))
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::BucketSet;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_bucket_overflow_and_reset() {
+        let start = Instant::now();
+        let mut buckets = BucketSet::new(&[(2, 1), (3, 10)]);
+
+        buckets.record(start);
+        buckets.record(start);
+        assert_eq!(buckets.counts_header(), "2:1,2:10");
+        assert!(buckets.retry_after(start).unwrap() <= Duration::from_secs(1));
+
+        // the short window rolls over, but the long one fills up
+        let later = start + Duration::from_secs(2);
+        assert!(buckets.retry_after(later).is_none());
+        buckets.record(later);
+        assert_eq!(buckets.counts_header(), "1:1,3:10");
+        assert!(buckets.retry_after(later).unwrap() > Duration::from_secs(7));
+    }
+}
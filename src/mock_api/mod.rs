@@ -0,0 +1,340 @@
+//! A local stand-in for the riot api. It serves
+//! fixture json files for the routes in `routes::ROUTES`
+//! and simulates the api's rate limiting, including
+//! the `X-App-Rate-Limit`, `X-Method-Rate-Limit`, `*-Count`
+//! and `Date` headers and 429 TOO MANY REQUESTS responses once
+//! a bucket overflows. Point a `lol_api::Context` at it with
+//! `ContextBuilder::base_uri` to run the crawler end to end
+//! without burning a real api key.
+//!
+//! Fixtures are looked up by mirroring the request path
+//! under the fixtures directory, e.g. a request for
+//! `/lol/match/v4/matches/1001` is served from
+//! `<fixtures>/lol/match/v4/matches/1001.json`. If that file
+//! doesn't exist, each segment of the path is swapped for `_default`
+//! in turn, starting from the last one, and the first of those
+//! files which exists is served instead. That saves writing the same
+//! matchlist for every account, e.g. `/lol/match/v5/matches/by-puuid/abc/ids`
+//! falls back to `<fixtures>/lol/match/v5/matches/by-puuid/_default/ids.json`.
+//! Otherwise the response is a 404.
+//!
+//! Requests may be prefixed with a routing value (e.g.
+//! `/na1/lol/...`), in which case each routing value gets
+//! its own application rate limits, like the live api.
+
+mod errors;
+mod limits;
+mod routes;
+pub use errors::*;
+
+// external uses
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+// my mods
+use limits::BucketSet;
+
+/// The configuration for the mock server
+#[derive(Debug, Clone)]
+pub struct MockApiConfig {
+    pub fixtures_dir : PathBuf,           // root directory of the fixture json files
+    pub app_limits : Vec<(u64, u64)>,     // application rate limits as (limit, window in seconds)
+    pub api_key : Option<String>,         // if set, any other key gets a 403 FORBIDDEN
+}
+
+impl Default for MockApiConfig {
+
+    /// Serves `./fixtures` with the application limits
+    /// of a development key and accepts any api key
+    fn default() -> MockApiConfig {
+        MockApiConfig {
+            fixtures_dir : PathBuf::from("./fixtures"),
+            app_limits : vec![(20, 1), (100, 120)],
+            api_key : None,
+        }
+    }
+}
+
+/// The state shared by every connection to the server
+#[derive(Debug)]
+struct ServerState {
+    config : MockApiConfig,
+    app_buckets : Mutex<HashMap<String, BucketSet>>,                       // keyed by routing value
+    method_buckets : Mutex<HashMap<(String, &'static str), BucketSet>>,    // keyed by routing value and method
+}
+
+/// Binds the mock server to the given address and spawns it onto
+/// the current tokio runtime.
+///
+/// # Arguments
+///
+/// `addr` - the address to listen on. Use port 0 to let the os pick a free port
+/// `config` - the configuration for the server
+///
+/// # Return
+///
+/// The address the server is listening on, or an error if it couldn't bind
+pub fn spawn(addr : SocketAddr, config : MockApiConfig) -> Result<SocketAddr> {
+
+    let state = Arc::new(ServerState {
+        config,
+        app_buckets : Mutex::new(HashMap::new()),
+        method_buckets : Mutex::new(HashMap::new()),
+    });
+
+    let make_service = make_service_fn(move |_conn| {
+        let state = state.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let state = state.clone();
+                async move { Ok::<_, Infallible>(state.respond(&request)) }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&addr)?.serve(make_service);
+    let local_addr = server.local_addr();
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            eprintln!("mock api server error: {}", e);
+        }
+    });
+
+    Ok(local_addr)
+}
+
+impl ServerState {
+
+    /// Builds the response for a single request
+    fn respond(&self, request : &Request<Body>) -> Response<Body> {
+
+        // the live api refuses requests without a (valid) key before anything else
+        let api_key = request.headers().get("X-Riot-Token").and_then(|v| v.to_str().ok());
+        match (api_key, &self.config.api_key) {
+            (None, _) => return error_response(StatusCode::UNAUTHORIZED, "Unauthorized"),
+            (Some(key), Some(expected)) if key != expected => return error_response(StatusCode::FORBIDDEN, "Forbidden"),
+            _ => {},
+        }
+
+        let (region, path) = routes::split_region(request.uri().path());
+        let region = region.unwrap_or("").to_string();
+        let route = match routes::find_route(path) {
+            Some(route) => route,
+            None => return error_response(StatusCode::NOT_FOUND, "Resource not found"),
+        };
+
+        // check and count against the simulated buckets
+        let now = Instant::now();
+        let mut app_buckets = self.app_buckets.lock().unwrap();
+        let mut method_buckets = self.method_buckets.lock().unwrap();
+        let app = app_buckets.entry(region.clone())
+            .or_insert_with(|| BucketSet::new(&self.config.app_limits));
+        let method = method_buckets.entry((region, route.method))
+            .or_insert_with(|| BucketSet::new(route.limits));
+
+        let limited = match (app.retry_after(now), method.retry_after(now)) {
+            (Some(retry_after), _) => Some((retry_after, "application")),
+            (None, Some(retry_after)) => Some((retry_after, "method")),
+            (None, None) => {
+                app.record(now);
+                method.record(now);
+                None
+            },
+        };
+
+        let mut response = match limited {
+            Some((retry_after, limit_type)) => {
+                let mut response = error_response(StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded");
+                let retry_secs = retry_after.as_secs() + if retry_after.subsec_nanos() > 0 { 1 } else { 0 };
+                insert_header(&mut response, "Retry-After", &retry_secs.to_string());
+                insert_header(&mut response, "X-Rate-Limit-Type", limit_type);
+                response
+            },
+            None => self.fixture_response(path),
+        };
+
+        insert_header(&mut response, "X-App-Rate-Limit", &app.limits_header());
+        insert_header(&mut response, "X-App-Rate-Limit-Count", &app.counts_header());
+        insert_header(&mut response, "X-Method-Rate-Limit", &method.limits_header());
+        insert_header(&mut response, "X-Method-Rate-Limit-Count", &method.counts_header());
+        response
+    }
+
+    /// Serves the fixture for the given path, falling back
+    /// to the `_default` fixtures described in the module docs
+    fn fixture_response(&self, path : &str) -> Response<Body> {
+
+        let segments : Vec<&str> = path.trim_start_matches('/').split('/').collect();
+        if segments.contains(&"..") {
+            return error_response(StatusCode::BAD_REQUEST, "Bad request");
+        }
+
+        let fixture_path = |segments : &[&str]| {
+            self.config.fixtures_dir.join(segments.join("/") + ".json")
+        };
+        let fallbacks = (0..segments.len()).rev().map(|idx| {
+            let mut segments = segments.clone();
+            segments[idx] = "_default";
+            fixture_path(&segments)
+        });
+        let mut candidates = std::iter::once(fixture_path(&segments)).chain(fallbacks);
+
+        match candidates.find_map(|candidate| std::fs::read(candidate).ok()) {
+            Some(body) => {
+                let mut response = Response::new(Body::from(body));
+                insert_header(&mut response, "Content-Type", "application/json;charset=utf-8");
+                insert_date(&mut response);
+                response
+            },
+            None => error_response(StatusCode::NOT_FOUND, "Data not found"),
+        }
+    }
+}
+
+/// Builds a response with a riot style error body
+fn error_response(status : StatusCode, message : &str) -> Response<Body> {
+    let body = format!("{{\"status\":{{\"message\":\"{}\",\"status_code\":{}}}}}", message, status.as_u16());
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    insert_header(&mut response, "Content-Type", "application/json;charset=utf-8");
+    insert_date(&mut response);
+    response
+}
+
+/// Sets the `Date` header in the same format as the live api
+fn insert_date(response : &mut Response<Body>) {
+    let date = chrono::Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+    insert_header(response, "Date", &date);
+}
+
+/// A small helper to save on typing out the header conversions
+fn insert_header(response : &mut Response<Body>, name : &'static str, value : &str) {
+    response.headers_mut().insert(name, value.parse().expect("invalid header value"));
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{spawn, MockApiConfig};
+    use crate::lol_api::{Context, Region};
+    use crate::lol_api::transport::{Transport, HttpTransport};
+    use reqwest::StatusCode;
+    use std::path::PathBuf;
+    use tokio::runtime::Runtime;
+
+    fn fixtures_config() -> MockApiConfig {
+        MockApiConfig {
+            fixtures_dir : PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures")),
+            ..MockApiConfig::default()
+        }
+    }
+
+    /// Runs a context against the mock server to check the fixtures
+    /// deserialize and that unknown data gives a 404
+    #[test]
+    fn test_context_against_mock_server() {
+
+        let mut rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let addr = spawn("127.0.0.1:0".parse().unwrap(), fixtures_config()).unwrap();
+            let ctx = Context::builder("test-key")
+                .base_uri(&format!("http://{}/{{host}}", addr))
+                .build();
+
+            let summoner_dto = ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, "hi").await.unwrap();
+            let matchlist_dto = ctx.try_query_match_v4_matchlist_by_account(Region::Na1, &summoner_dto.account_id).await.unwrap();
+            let match_id = matchlist_dto.matches[0].game_id;
+            let match_dto = ctx.try_query_match_v4_match_by_id(Region::Na1, match_id).await.unwrap();
+            assert_eq!(match_dto.participants.len(), 10);
+
+            assert!(ctx.try_query_match_v4_match_by_id(Region::Na1, 1).await.is_err());
+        });
+    }
+
+    /// Checks that the server refuses requests with a 429 and
+    /// the rate limit headers once the application bucket is full
+    #[test]
+    fn test_app_limit_overflow() {
+
+        let mut rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let config = MockApiConfig {
+                app_limits : vec![(2, 10)],
+                ..fixtures_config()
+            };
+            let addr = spawn("127.0.0.1:0".parse().unwrap(), config).unwrap();
+            let uri = format!("http://{}/na1/lol/summoner/v4/summoners/by-name/hi", addr);
+            let transport = HttpTransport::new();
+
+            for count in 1..=2 {
+                let response = transport.get(&uri, "test-key").await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                assert_eq!(response.headers()["X-App-Rate-Limit"], "2:10");
+                assert_eq!(response.headers()["X-App-Rate-Limit-Count"], format!("{}:10", count).as_str());
+                assert_eq!(response.headers()["X-Method-Rate-Limit"], "2000:60");
+                assert!(response.headers().contains_key("Date"));
+            }
+
+            let response = transport.get(&uri, "test-key").await.unwrap();
+            assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+            assert_eq!(response.headers()["X-Rate-Limit-Type"], "application");
+            assert!(response.headers().contains_key("Retry-After"));
+
+            // other regions have their own buckets
+            let uri = format!("http://{}/euw1/lol/summoner/v4/summoners/by-name/hi", addr);
+            let response = transport.get(&uri, "test-key").await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        });
+    }
+
+    /// Checks that a context which waits for capacity never
+    /// overflows the server's buckets, even with many concurrent queries
+    #[test]
+    fn test_context_stays_under_app_limit() {
+
+        let mut rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let config = MockApiConfig {
+                app_limits : vec![(5, 1)],
+                ..fixtures_config()
+            };
+            let addr = spawn("127.0.0.1:0".parse().unwrap(), config).unwrap();
+            let ctx = Context::builder("test-key")
+                .base_uri(&format!("http://{}/{{host}}", addr))
+                .build();
+
+            // distinct queries, so none of them are coalesced
+            let names = ["hi", "MockTop", "MockTop2", "MockJungle", "MockJungle2", "MockMid",
+                         "MockMid2", "MockCarry", "MockCarry2", "MockSupport"];
+            let queries = names.iter().map(|name| ctx.try_query_summoner_v4_by_summoner_name(Region::Na1, name));
+            for result in futures::future::join_all(queries).await {
+                assert!(result.is_ok());
+            }
+        });
+    }
+
+    /// Checks the server only accepts the configured key
+    #[test]
+    fn test_wrong_key_forbidden() {
+
+        let mut rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let config = MockApiConfig {
+                api_key : Some("right-key".to_string()),
+                ..fixtures_config()
+            };
+            let addr = spawn("127.0.0.1:0".parse().unwrap(), config).unwrap();
+            let uri = format!("http://{}/lol/summoner/v4/summoners/by-name/hi", addr);
+            let transport = HttpTransport::new();
+
+            assert_eq!(transport.get(&uri, "wrong-key").await.unwrap().status(), StatusCode::FORBIDDEN);
+            assert_eq!(transport.get(&uri, "right-key").await.unwrap().status(), StatusCode::OK);
+        });
+    }
+}
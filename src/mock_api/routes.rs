@@ -0,0 +1,92 @@
+//! The table of riot api routes the mock server knows
+//! how to serve, along with the method rate limits
+//! it enforces for each of them.
+
+/// A single route (i.e. riot api method) served by the mock server
+#[derive(Debug)]
+pub struct Route {
+    pub method : &'static str,            // name of the method, keys the method rate limits
+    pub pattern : &'static str,           // the path, where each `{}` matches a single path segment
+    pub limits : &'static [(u64, u64)],   // method rate limits as (limit, window in seconds)
+}
+
+/// Every route served by the mock server. The method limits
+/// mirror the ones the live api hands out to development keys.
+pub const ROUTES : &[Route] = &[
+    Route { method : "summoner-v4.getBySummonerName", pattern : "/lol/summoner/v4/summoners/by-name/{}", limits : &[(2000, 60)] },
+    Route { method : "summoner-v4.getByAccountId", pattern : "/lol/summoner/v4/summoners/by-account/{}", limits : &[(2000, 60)] },
+    Route { method : "summoner-v4.getByPUUID", pattern : "/lol/summoner/v4/summoners/by-puuid/{}", limits : &[(2000, 60)] },
+    Route { method : "summoner-v4.getBySummonerId", pattern : "/lol/summoner/v4/summoners/{}", limits : &[(2000, 60)] },
+    Route { method : "match-v4.getMatchlist", pattern : "/lol/match/v4/matchlists/by-account/{}", limits : &[(1000, 10)] },
+    Route { method : "match-v4.getMatch", pattern : "/lol/match/v4/matches/{}", limits : &[(500, 10)] },
+    Route { method : "match-v5.getMatchIdsByPUUID", pattern : "/lol/match/v5/matches/by-puuid/{}/ids", limits : &[(2000, 10)] },
+    Route { method : "match-v5.getMatch", pattern : "/lol/match/v5/matches/{}", limits : &[(2000, 10)] },
+    Route { method : "champion-mastery-v4.getAllChampionMasteries", pattern : "/lol/champion-mastery/v4/champion-masteries/by-summoner/{}", limits : &[(20000, 10)] },
+    Route { method : "account-v1.getByRiotId", pattern : "/riot/account/v1/accounts/by-riot-id/{}/{}", limits : &[(1000, 60)] },
+    Route { method : "account-v1.getByPuuid", pattern : "/riot/account/v1/accounts/by-puuid/{}", limits : &[(1000, 60)] },
+];
+
+/// Splits the optional routing value prefix (e.g. the `/na1` in
+/// `/na1/lol/match/v4/matches/1`) off of a request path, so a single
+/// server can simulate several regions.
+///
+/// # Return
+///
+/// The routing value, if there was one, and the remaining riot api path
+pub fn split_region(path : &str) -> (Option<&str>, &str) {
+    let trimmed = path.trim_start_matches('/');
+    match trimmed.find('/') {
+        Some(idx) if !trimmed.starts_with("lol/") && !trimmed.starts_with("riot/") => {
+            (Some(&trimmed[..idx]), &trimmed[idx..])
+        },
+        _ => (None, path),
+    }
+}
+
+/// Finds the route which matches the given riot api path
+///
+/// # Return
+///
+/// The matching route, or `None` if the mock server doesn't serve the path
+pub fn find_route(path : &str) -> Option<&'static Route> {
+    ROUTES.iter().find(|route| matches_pattern(route.pattern, path))
+}
+
+/// Checks a path against a route pattern segment by segment
+fn matches_pattern(pattern : &str, path : &str) -> bool {
+    let mut pattern_segments = pattern.split('/');
+    let mut path_segments = path.split('/');
+    loop {
+        match (pattern_segments.next(), path_segments.next()) {
+            (None, None) => return true,
+            (Some("{}"), Some(segment)) if !segment.is_empty() => {},
+            (Some(expected), Some(segment)) if expected == segment => {},
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{split_region, find_route};
+
+    #[test]
+    fn test_split_region() {
+        assert_eq!(split_region("/na1/lol/match/v4/matches/1"), (Some("na1"), "/lol/match/v4/matches/1"));
+        assert_eq!(split_region("/lol/match/v4/matches/1"), (None, "/lol/match/v4/matches/1"));
+    }
+
+    #[test]
+    fn test_find_route() {
+        assert_eq!(find_route("/lol/match/v4/matches/1").unwrap().method, "match-v4.getMatch");
+        assert_eq!(find_route("/lol/summoner/v4/summoners/by-name/hi").unwrap().method, "summoner-v4.getBySummonerName");
+        assert_eq!(find_route("/lol/match/v5/matches/by-puuid/p/ids").unwrap().method, "match-v5.getMatchIdsByPUUID");
+        assert_eq!(find_route("/lol/summoner/v4/summoners/by-puuid/p").unwrap().method, "summoner-v4.getByPUUID");
+        assert_eq!(find_route("/lol/summoner/v4/summoners/s").unwrap().method, "summoner-v4.getBySummonerId");
+        assert_eq!(find_route("/riot/account/v1/accounts/by-riot-id/hi/NA1").unwrap().method, "account-v1.getByRiotId");
+        assert_eq!(find_route("/lol/champion-mastery/v4/champion-masteries/by-summoner/s").unwrap().method, "champion-mastery-v4.getAllChampionMasteries");
+        assert!(find_route("/lol/match/v4/matches/").is_none());
+        assert!(find_route("/lol/match/v4/matches/1/extra").is_none());
+    }
+}
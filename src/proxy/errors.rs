@@ -0,0 +1,13 @@
+error_chain!{
+
+    links {
+        LolApi(crate::lol_api::Error, crate::lol_api::ErrorKind);
+    }
+
+    foreign_links {
+        Io(::std::io::Error);
+        Hyper(::hyper::Error);
+        AddrParse(::std::net::AddrParseError);
+    }
+
+}
@@ -0,0 +1,250 @@
+//! A local reverse proxy for the riot api. It serves the riot
+//! api paths in `routes::ROUTES` on a local address and forwards
+//! each request upstream through a single `lol_api::Context`, which
+//! sends the real api key and keeps every client of the proxy under
+//! the rate limits together. Scripts, notebooks, etc. can then query
+//! the proxy without holding the key or knowing about rate limits.
+//!
+//! Requests are prefixed with the routing value of the host to send
+//! them to, e.g. `/na1/lol/summoner/v4/summoners/by-name/hi`. Requests
+//! for a regional service (e.g. match-v5) may use either the platform
+//! (e.g. `/na1/...`) or the cluster (e.g. `/americas/...`).
+//!
+//! Upstream responses are passed on as they are. Queries the context
+//! gives up on come back as a 429 TOO MANY REQUESTS (with a `Retry-After`)
+//! if they were rate limited, a 504 GATEWAY TIMEOUT if upstream didn't
+//! respond in time, or a 502 BAD GATEWAY for any other failure.
+
+mod errors;
+mod routes;
+pub use errors::*;
+
+// external uses
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+// my mods
+use crate::lol_api::{self, Context, Host};
+use crate::lol_api::transport::RawResponse;
+
+/// Binds the proxy to the given address and spawns it onto
+/// the current tokio runtime.
+///
+/// # Arguments
+///
+/// `addr` - the address to listen on. Use port 0 to let the os pick a free port
+/// `ctx` - the context which forwards the requests upstream
+///
+/// # Return
+///
+/// The address the proxy is listening on, or an error if it couldn't bind
+pub fn spawn(addr : SocketAddr, ctx : Context) -> Result<SocketAddr> {
+
+    let ctx = Arc::new(ctx);
+    let make_service = make_service_fn(move |_conn| {
+        let ctx = ctx.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let ctx = ctx.clone();
+                async move { Ok::<_, Infallible>(respond(&ctx, request).await) }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&addr)?.serve(make_service);
+    let local_addr = server.local_addr();
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            eprintln!("riot api proxy error: {}", e);
+        }
+    });
+
+    Ok(local_addr)
+}
+
+/// Forwards a single request upstream and builds the response for it
+async fn respond(ctx : &Context, request : Request<Body>) -> Response<Body> {
+
+    if request.method() != Method::GET {
+        return error_response(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed");
+    }
+
+    let (routing_value, path) = routes::split_routing_value(request.uri().path());
+    let host = match routing_value.map(|routing_value| routing_value.parse::<Host>()) {
+        Some(Ok(host)) => host,
+        _ => return error_response(StatusCode::NOT_FOUND, "Unknown routing value"),
+    };
+    let route = match routes::find_route(path) {
+        Some(route) => route,
+        None => return error_response(StatusCode::NOT_FOUND, "Resource not found"),
+    };
+
+    // send regional services to the platform's cluster
+    let host = match host {
+        Host::Platform(region) => route.service.host(region),
+        Host::Regional(_) if route.service.is_regional() => host,
+        Host::Regional(_) => return error_response(StatusCode::BAD_REQUEST, "Service isn't served from a regional cluster"),
+    };
+    let path = match request.uri().query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_string(),
+    };
+
+    match ctx.query_raw(host, route.service, route.method, &path).await {
+        Ok(response) => forward_response(&response),
+        Err(e) => failed_query_response(&e),
+    }
+}
+
+/// Passes an upstream response on to the client
+fn forward_response(upstream : &RawResponse) -> Response<Body> {
+    let mut response = Response::new(Body::from(upstream.body().to_vec()));
+    *response.status_mut() = upstream.status();
+    for (name, value) in upstream.headers() {
+        // hyper works the framing of the response out for itself
+        if name != "content-length" && name != "transfer-encoding" && name != "connection" {
+            response.headers_mut().append(name, value.clone());
+        }
+    }
+    response
+}
+
+/// Builds the response for a query the context gave up on
+fn failed_query_response(e : &lol_api::Error) -> Response<Body> {
+
+    use lol_api::ErrorKind;
+
+    // the error the query actually failed with, under any chained along the way (e.g. by the retries)
+    let mut cause : Option<&(dyn std::error::Error + 'static)> = Some(e);
+    let mut e = e;
+    while let Some(err) = cause {
+        match err.downcast_ref::<lol_api::Error>() {
+            Some(api_err) if !matches!(api_err.kind(), ErrorKind::Msg(_)) => {
+                e = api_err;
+                break;
+            },
+            _ => cause = err.source(),
+        }
+    }
+
+    match e.kind() {
+        ErrorKind::BadRequest(status, _, body) | ErrorKind::Unauthorized(status, _, body)
+        | ErrorKind::NotFound(status, _, body) | ErrorKind::UnsupportedMediaType(status, _, body) => {
+            let mut response = Response::new(Body::from(body.clone()));
+            *response.status_mut() = *status;
+            insert_header(&mut response, "Content-Type", "application/json;charset=utf-8");
+            response
+        },
+        ErrorKind::ResponseStatus(status) => error_response(*status, status.canonical_reason().unwrap_or("Error")),
+        ErrorKind::RateLimited(..) | ErrorKind::EndpointNotReady(_) => {
+            let mut response = error_response(StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded");
+            let retry_after = e.retry_time().unwrap_or_default();
+            let retry_secs = retry_after.as_secs() + if retry_after.subsec_nanos() > 0 { 1 } else { 0 };
+            insert_header(&mut response, "Retry-After", &retry_secs.max(1).to_string());
+            response
+        },
+        ErrorKind::Timeout(_) => error_response(StatusCode::GATEWAY_TIMEOUT, "Upstream timed out"),
+        _ => error_response(StatusCode::BAD_GATEWAY, &format!("Upstream query failed: {}", e)),
+    }
+}
+
+/// Builds a response with a riot style error body
+fn error_response(status : StatusCode, message : &str) -> Response<Body> {
+    let body = serde_json::json!({ "status" : { "message" : message, "status_code" : status.as_u16() } });
+    let mut response = Response::new(Body::from(body.to_string()));
+    *response.status_mut() = status;
+    insert_header(&mut response, "Content-Type", "application/json;charset=utf-8");
+    response
+}
+
+/// A small helper to save on typing out the header conversions
+fn insert_header(response : &mut Response<Body>, name : &'static str, value : &str) {
+    response.headers_mut().insert(name, value.parse().expect("invalid header value"));
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::spawn;
+    use crate::lol_api::{Context, RetryPolicy, SummonerDto};
+    use crate::lol_api::transport::MockTransport;
+    use crate::mock_api::{self, MockApiConfig};
+    use reqwest::StatusCode;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use tokio::runtime::Runtime;
+
+    /// Runs the proxy in front of the mock server, which only
+    /// takes the key the proxy holds
+    #[test]
+    fn test_proxy_against_mock_server() {
+
+        let mut rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let config = MockApiConfig {
+                fixtures_dir : PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures")),
+                app_limits : vec![(2, 10)],
+                api_key : Some("real-key".to_string()),
+            };
+            let upstream = mock_api::spawn("127.0.0.1:0".parse().unwrap(), config).unwrap();
+            let ctx = Context::builder("real-key")
+                .base_uri(&format!("http://{}/{{host}}", upstream))
+                .wait_for_capacity(false)
+                .retry_policy(RetryPolicy::none())
+                .build();
+            let proxy = spawn("127.0.0.1:0".parse().unwrap(), ctx).unwrap();
+            let get = |path : &str| reqwest::get(reqwest::Url::parse(&format!("http://{}{}", proxy, path)).unwrap());
+
+            // clients don't send a key at all
+            let response = get("/na1/lol/summoner/v4/summoners/by-name/hi").await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.json::<SummonerDto>().await.unwrap().name, "hi");
+
+            // regional services go to the platform's cluster
+            let response = get("/na1/lol/match/v5/matches/NA1_3400000001").await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let response = get("/na1/lol/match/v4/matches/1").await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            assert_eq!(get("/lol/match/v4/matches/1").await.unwrap().status(), StatusCode::NOT_FOUND);
+            assert_eq!(get("/na1/lol/status/v4/platform-data").await.unwrap().status(), StatusCode::NOT_FOUND);
+
+            // the proxy turns the next query away itself, rather than earning a 429 from upstream
+            let response = get("/na1/lol/summoner/v4/summoners/by-name/MockTop").await.unwrap();
+            assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+            assert!(response.headers().contains_key("Retry-After"));
+            assert!(!response.headers().contains_key("X-Rate-Limit-Type"));
+        });
+    }
+
+    /// Checks that paths with dot segments, which the upstream uri would
+    /// resolve to some other path, are turned away rather than forwarded
+    #[test]
+    fn test_proxy_rejects_dot_segments() {
+
+        let mut rt = Runtime::new().unwrap();
+        let transport = Arc::new(MockTransport::new());
+        let ctx = Context::with_transport("real-key", transport.clone());
+
+        rt.block_on(async {
+            let proxy = spawn("127.0.0.1:0".parse().unwrap(), ctx).unwrap();
+
+            // http clients resolve dot segments themselves, so write the requests out by hand
+            for path in ["/na1/lol/league/v4/entries/../../X", "/na1/lol/league/v4/entries/%2e%2e/%2E%2E/X"].iter() {
+                let mut stream = TcpStream::connect(proxy).await.unwrap();
+                let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", path, proxy);
+                stream.write_all(request.as_bytes()).await.unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).await.unwrap();
+                assert!(response.starts_with("HTTP/1.1 404"), "{} got {}", path, response);
+            }
+        });
+
+        assert!(transport.requests().is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+//! The table of riot api routes the proxy forwards, along
+//! with the service and method each of them is rate limited as.
+
+// my mods
+use crate::lol_api::{Service, summoner_v4, match_v4, match_v5, league_v4, spectator_v4, champion_mastery_v4, account_v1};
+
+/// A single route (i.e. riot api method) forwarded by the proxy
+#[derive(Debug)]
+pub struct Route {
+    pub service : Service,
+    pub method : u32,                     // the u32 representation of the method, as the context takes it
+    pub pattern : &'static str,           // the path, where each `{}` matches a single path segment
+}
+
+/// Every route forwarded by the proxy
+pub const ROUTES : &[Route] = &[
+    Route { service : Service::SummonerV4, method : summoner_v4::Method::ByName as u32, pattern : "/lol/summoner/v4/summoners/by-name/{}" },
+    Route { service : Service::SummonerV4, method : summoner_v4::Method::ByAccount as u32, pattern : "/lol/summoner/v4/summoners/by-account/{}" },
+    Route { service : Service::SummonerV4, method : summoner_v4::Method::ByPuuid as u32, pattern : "/lol/summoner/v4/summoners/by-puuid/{}" },
+    Route { service : Service::SummonerV4, method : summoner_v4::Method::BySummonerId as u32, pattern : "/lol/summoner/v4/summoners/{}" },
+    Route { service : Service::MatchV4, method : match_v4::Method::MatchlistByAccount as u32, pattern : "/lol/match/v4/matchlists/by-account/{}" },
+    Route { service : Service::MatchV4, method : match_v4::Method::MatchById as u32, pattern : "/lol/match/v4/matches/{}" },
+    Route { service : Service::MatchV4, method : match_v4::Method::TimelineByMatchId as u32, pattern : "/lol/match/v4/timelines/by-match/{}" },
+    Route { service : Service::MatchV5, method : match_v5::Method::MatchIdsByPuuid as u32, pattern : "/lol/match/v5/matches/by-puuid/{}/ids" },
+    Route { service : Service::MatchV5, method : match_v5::Method::MatchById as u32, pattern : "/lol/match/v5/matches/{}" },
+    Route { service : Service::LeagueV4, method : league_v4::Method::ChallengerLeague as u32, pattern : "/lol/league/v4/challengerleagues/by-queue/{}" },
+    Route { service : Service::LeagueV4, method : league_v4::Method::GrandmasterLeague as u32, pattern : "/lol/league/v4/grandmasterleagues/by-queue/{}" },
+    Route { service : Service::LeagueV4, method : league_v4::Method::MasterLeague as u32, pattern : "/lol/league/v4/masterleagues/by-queue/{}" },
+    Route { service : Service::LeagueV4, method : league_v4::Method::EntriesBySummoner as u32, pattern : "/lol/league/v4/entries/by-summoner/{}" },
+    Route { service : Service::LeagueV4, method : league_v4::Method::Entries as u32, pattern : "/lol/league/v4/entries/{}/{}/{}" },
+    Route { service : Service::SpectatorV4, method : spectator_v4::Method::ActiveGameBySummoner as u32, pattern : "/lol/spectator/v4/active-games/by-summoner/{}" },
+    Route { service : Service::SpectatorV4, method : spectator_v4::Method::FeaturedGames as u32, pattern : "/lol/spectator/v4/featured-games" },
+    Route { service : Service::ChampionMasteryV4, method : champion_mastery_v4::Method::BySummoner as u32, pattern : "/lol/champion-mastery/v4/champion-masteries/by-summoner/{}" },
+    Route { service : Service::ChampionMasteryV4, method : champion_mastery_v4::Method::BySummonerAndChampion as u32, pattern : "/lol/champion-mastery/v4/champion-masteries/by-summoner/{}/by-champion/{}" },
+    Route { service : Service::ChampionMasteryV4, method : champion_mastery_v4::Method::ScoreBySummoner as u32, pattern : "/lol/champion-mastery/v4/scores/by-summoner/{}" },
+    Route { service : Service::AccountV1, method : account_v1::Method::ByRiotId as u32, pattern : "/riot/account/v1/accounts/by-riot-id/{}/{}" },
+    Route { service : Service::AccountV1, method : account_v1::Method::ByPuuid as u32, pattern : "/riot/account/v1/accounts/by-puuid/{}" },
+];
+
+/// Splits the routing value prefix (e.g. the `/na1` in
+/// `/na1/lol/match/v4/matches/1`) off of a request path
+///
+/// # Return
+///
+/// The routing value, if there was one, and the remaining riot api path
+pub fn split_routing_value(path : &str) -> (Option<&str>, &str) {
+    let trimmed = path.trim_start_matches('/');
+    match trimmed.find('/') {
+        Some(idx) if !trimmed.starts_with("lol/") && !trimmed.starts_with("riot/") => {
+            (Some(&trimmed[..idx]), &trimmed[idx..])
+        },
+        _ => (None, path),
+    }
+}
+
+/// Finds the route which matches the given riot api path
+///
+/// # Return
+///
+/// The matching route, or `None` if the proxy doesn't forward the path
+pub fn find_route(path : &str) -> Option<&'static Route> {
+    ROUTES.iter().find(|route| matches_pattern(route.pattern, path))
+}
+
+/// Checks a path against a route pattern segment by segment
+fn matches_pattern(pattern : &str, path : &str) -> bool {
+    let mut pattern_segments = pattern.split('/');
+    let mut path_segments = path.split('/');
+    loop {
+        match (pattern_segments.next(), path_segments.next()) {
+            (None, None) => return true,
+            (Some("{}"), Some(segment)) if !segment.is_empty() && !is_dot_segment(segment) => {},
+            (Some(expected), Some(segment)) if expected == segment => {},
+            _ => return false,
+        }
+    }
+}
+
+/// Checks whether a path segment is (or would be parsed as) a `.` or `..`
+/// segment. The upstream uri resolves those, which would send the query
+/// to some other path than the route it matched.
+fn is_dot_segment(segment : &str) -> bool {
+    if segment.contains('\\') {
+        // parsed as a path separator in http uris
+        return true;
+    }
+    let decoded = segment.replace("%2e", ".").replace("%2E", ".");
+    decoded == "." || decoded == ".."
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{split_routing_value, find_route};
+    use crate::lol_api::{Service, summoner_v4, champion_mastery_v4};
+
+    #[test]
+    fn test_find_route() {
+        assert_eq!(split_routing_value("/na1/lol/match/v4/matches/1"), (Some("na1"), "/lol/match/v4/matches/1"));
+        assert_eq!(split_routing_value("/lol/match/v4/matches/1"), (None, "/lol/match/v4/matches/1"));
+
+        let route = find_route("/lol/summoner/v4/summoners/by-name/hi").unwrap();
+        assert_eq!((route.service, route.method), (Service::SummonerV4, summoner_v4::Method::ByName as u32));
+        let route = find_route("/lol/summoner/v4/summoners/s").unwrap();
+        assert_eq!(route.method, summoner_v4::Method::BySummonerId as u32);
+        let route = find_route("/lol/champion-mastery/v4/champion-masteries/by-summoner/s/by-champion/1").unwrap();
+        assert_eq!(route.method, champion_mastery_v4::Method::BySummonerAndChampion as u32);
+        assert_eq!(find_route("/lol/match/v5/matches/by-puuid/p/ids").unwrap().service, Service::MatchV5);
+        assert!(find_route("/lol/match/v4/matches/").is_none());
+        assert!(find_route("/lol/status/v4/platform-data").is_none());
+    }
+
+    #[test]
+    fn test_dot_segments_never_match() {
+        assert!(find_route("/lol/league/v4/entries/RANKED_SOLO_5x5/GOLD/I").is_some());
+        assert!(find_route("/lol/league/v4/entries/../../X").is_none());
+        assert!(find_route("/lol/league/v4/entries/%2e%2E/%2E./X").is_none());
+        assert!(find_route("/lol/league/v4/entries/./x/y").is_none());
+        assert!(find_route("/lol/summoner/v4/summoners/by-name/..%5Cx").is_some());
+        assert!(find_route("/lol/summoner/v4/summoners/by-name/..\\x").is_none());
+        assert!(find_route("/lol/summoner/v4/summoners/by-name/...").is_some());
+    }
+}
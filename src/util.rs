@@ -6,3 +6,14 @@ pub fn get_key() -> String {
         .expect("Can't open file <project root>/key.txt. Please put the riot api key in this file.")
         .trim().to_string()
 }
+
+/// Loads every api key from key.txt, one per line, for a
+/// context which spreads its queries across all of them.
+pub fn get_keys() -> Vec<String> {
+    std::fs::read_to_string("./key.txt")
+        .expect("Can't open file <project root>/key.txt. Please put the riot api key in this file.")
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}